@@ -0,0 +1,407 @@
+//! A running game with the state of the user interface. Everything the window
+//! does goes through `Game`, so the game can also be played without one.
+
+use game::*;
+use graphics::{Align, Graphics, Layout, TextMetrics};
+
+const ZOOM_MIN: f64 = 0.6;
+const ZOOM_MAX: f64 = 5.0;
+const ZOOM_STEP: f64 = 0.2;
+const ZOOM_DEFAULT: f64 = 1.6;
+
+pub struct Game {
+    pub session: Session,
+    pub state: State,
+    pub zoom: f64,
+    pub shift: [f64;2],
+    /// The action under the mouse. Set by the renderer.
+    pub hover_action: Option<Action>,
+    /// Events, that haven't been closed yet.
+    pub events: Vec<Event>,
+    /// The result of a finished game with the high scores and its rank.
+    pub summary: Option<(Summary, HighScores, Option<usize>)>,
+    /// File the high scores are kept in, if any.
+    pub high_scores: Option<String>,
+    replay_log: Option<String>,
+    recorder: Option<Recorder>,
+}
+
+impl Game {
+    pub fn new(session: Session) -> Game {
+        Game {
+            session: session,
+            state: State::Def,
+            zoom: ZOOM_DEFAULT,
+            shift: [0.0, 0.0],
+            hover_action: None,
+            events: Vec::new(),
+            summary: None,
+            high_scores: None,
+            replay_log: None,
+            recorder: None,
+        }
+    }
+
+    pub fn from_save(save: SaveGame) -> Game {
+        let mut game = Game::new(Session::from_save(save.clone()));
+        game.state = save.state;
+        game.zoom = save.zoom;
+        game.shift = save.shift;
+        game
+    }
+
+    pub fn to_save(&self) -> SaveGame {
+        self.session.to_save(self.state.clone(), self.zoom, self.shift)
+    }
+
+    /// Continue a saved game. Files for the high scores and the replay are
+    /// kept, the replay starts again.
+    pub fn load(&mut self, save: SaveGame) {
+        let mut game = Game::from_save(save);
+        game.high_scores = self.high_scores.take();
+        game.replay_log = self.replay_log.take();
+        *self = game;
+        self.start_log();
+    }
+
+    /// Record all inputs from now on into a replay file.
+    pub fn record(&mut self, file: &str) {
+        self.replay_log = Some(file.to_string());
+        self.start_log();
+    }
+
+    fn start_log(&mut self) {
+        self.recorder = self.replay_log.as_ref().and_then(|file| {
+            let start = self.session.to_save(State::Def, 1.0, [0.0, 0.0]);
+            match Recorder::create(file, &start) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    println!("Recording failed: {}", e);
+                    None
+                }
+            }
+        });
+    }
+
+    pub fn finished(&self) -> bool {
+        self.session.sim.game_over || self.session.sim.victory
+    }
+
+    /// Carry out an input of the player. Returns the report, if the turn
+    /// ended.
+    pub fn perform(&mut self, input: Input) -> Option<TurnReport> {
+        if let Some(ref mut r) = self.recorder {
+            if let Err(e) = r.record(self.session.sim.turn, &input) {
+                println!("Recording failed: {}", e);
+            }
+        }
+        let report = self.session.perform(&input);
+        if let Some(ref report) = report {
+            self.events.extend(report.events.iter().cloned());
+            if (report.game_over || report.victory) && self.summary.is_none() {
+                self.finish();
+            }
+        }
+        if self.state==State::BuildRoad && !self.session.map.stock.contains(&ROAD_COST) {
+            self.state = State::Def;
+        }
+        report
+    }
+
+    /// Build the summary and enter it into the high scores.
+    fn finish(&mut self) {
+        let s = Summary::new(&self.session.map, &self.session.sim);
+        let mut scores = match self.high_scores {
+            Some(ref file) => HighScores::load(file).unwrap_or_else(|e| {
+                println!("{}", e);
+                HighScores::default()
+            }),
+            None => HighScores::default(),
+        };
+        let rank = scores.insert(HighScore {
+            score: s.score,
+            turns: s.turns,
+            seed: self.session.rng.seed,
+            victory: s.victory,
+        });
+        if let Some(ref file) = self.high_scores {
+            if let Err(e) = scores.save(file) {
+                println!("Saving the high scores failed: {}", e);
+            }
+        }
+        self.summary = Some((s, scores, rank));
+    }
+
+    /// Click on something. While events are shown or after the game has
+    /// ended, only closing events is possible. Returns the report, if the turn
+    /// ended.
+    pub fn click(&mut self, action: Action) -> Option<TurnReport> {
+        let blocked = !self.events.is_empty() || self.finished();
+        let input = match action {
+            _ if blocked && action!=Action::CloseEvent => None,
+            Action::CloseEvent => {
+                if !self.events.is_empty() {
+                    self.events.remove(0);
+                }
+                None
+            }
+            Action::Deck(c, i) => {
+                self.state = State::PlaceCard(c, i);
+                None
+            }
+            Action::Field(p) => match self.state.clone() {
+                State::PlaceCard(_, i) => {
+                    self.state = State::Def;
+                    Some(Input::Place(p, i))
+                }
+                _ => None,
+            },
+            Action::EnactLaw => match self.state.clone() {
+                State::PlaceCard(_, i) => {
+                    self.state = State::Def;
+                    Some(Input::Law(i))
+                }
+                _ => None,
+            },
+            Action::RepealLaw(i) => Some(Input::Repeal(i)),
+            Action::Prioritise(p) => Some(Input::Prioritise(p)),
+            Action::StartRoad => {
+                self.state = State::BuildRoad;
+                None
+            }
+            Action::StartDemolish => {
+                self.state = State::Demolish;
+                None
+            }
+            Action::Demolish(p) => {
+                self.state = State::Def;
+                Some(Input::Demolish(p))
+            }
+            Action::Road(p) => Some(Input::Road(p)),
+            Action::EndTurn => Some(Input::EndTurn),
+        };
+        input.and_then(|input| self.perform(input))
+    }
+
+    pub fn undo(&mut self) {
+        if self.events.is_empty() && !self.finished() {
+            self.state = State::Def;
+            self.perform(Input::Undo);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.events.is_empty() && !self.finished() {
+            self.state = State::Def;
+            self.perform(Input::Redo);
+        }
+    }
+
+    /// Stop placing a card, building roads or demolishing.
+    pub fn cancel(&mut self) {
+        self.state = State::Def;
+    }
+
+    pub fn zoom_by(&mut self, steps: f64) {
+        self.zoom = clamp(ZOOM_MIN, self.zoom+steps*ZOOM_STEP, ZOOM_MAX);
+    }
+
+    pub fn pan(&mut self, d: [f64;2]) {
+        self.shift = [self.shift[0]+d[0], self.shift[1]+d[1]];
+    }
+
+    /// The whole screen for a window of size `view`, with text measured by
+    /// `metrics`.
+    pub fn graphics<M: TextMetrics>(&self, view: [f64;2], mouse_pos: [f64;2], metrics: &mut M)
+        -> Graphics
+    {
+        let session = &self.session;
+        let state = &self.state;
+        let v = view;
+
+        let field = {
+            session.map.build_graphics(state)
+                .translate(self.shift)
+                .scale(self.zoom)
+        };
+
+        let ui = {
+            let cards = session.deck.draw(v[0]/2.0, state).scale(2.0);
+            let height = cards.bbox(metrics).map_or(0.0, |b| b[1]+b[3]);
+            let r = Graphics::Rectangle(v[0],height)
+                .color([0.3,0.3,0.3,1.0]);
+            Graphics::Group(vec![r, cards])
+                .anchor(v, Align::Start, Align::End, 0.0)
+        };
+
+        let status = {
+            let txt = if session.sim.game_over {
+                "Game over!".to_string()
+            } else if session.sim.victory {
+                "Victory!".to_string()
+            } else {
+                format!("Turn {}   Population {}   Workers needed {}   Seed {}",
+                        session.sim.turn, session.map.pops(), session.map.nec_pops(),
+                        session.rng.seed)
+            };
+            let goals: Vec<String> = session.map.victory_goals().iter()
+                .map(|g| g.description())
+                .collect();
+            Graphics::column(vec![
+                Graphics::Text(14, txt),
+                Graphics::Text(14, session.map.stock.to_string()),
+                Graphics::Text(10, format!("Goals: {}", goals.join(", "))),
+            ], 4.0)
+        };
+
+        let mut panel = Vec::new();
+        if !self.finished() {
+            let def = state==&State::Def;
+            let buttons = vec![
+                button("Demolish", [0.6,0.3,0.3,1.0],
+                       if def && !session.map.cards.is_empty() { Some(Action::StartDemolish) } else { None }),
+                button("Build road", [0.5,0.4,0.3,1.0],
+                       if def && session.map.stock.contains(&ROAD_COST) { Some(Action::StartRoad) } else { None }),
+                button("End turn", [0.3,0.3,0.3,1.0],
+                       if def { Some(Action::EndTurn) } else { None }),
+            ];
+            panel.push(Graphics::row(buttons, 10.0));
+        }
+        panel.push(session.map.draw_laws(state));
+        let panel = Graphics::Arrange(Layout::Column(10.0, Align::End), panel);
+
+        // The panel goes below the status, if they don't fit next to each other.
+        let status_box = status.bbox(metrics).unwrap_or([0.0;4]);
+        let panel_width = panel.bbox(metrics).map_or(0.0, |b| b[2]);
+        let top = if status_box[2]+panel_width+30.0>v[0] { status_box[3]+10.0 } else { 0.0 };
+        let status = status.anchor(v, Align::Start, Align::Start, 10.0);
+        let panel = panel.anchor(v, Align::End, Align::Start, 10.0)
+            .translate([0.0, top]);
+
+        let mut graphics = vec![field, ui, status, panel];
+        if let &State::PlaceCard(ref c, _) = state {
+            graphics.push(c.draw().translate(mouse_pos));
+        }
+        if let Some(ev) = self.events.first() {
+            graphics.push(ev.draw().anchor(v, Align::Center, Align::Center, 0.0));
+        } else if let Some((ref s, ref scores, rank)) = self.summary {
+            graphics.push(s.draw(scores, rank).anchor(v, Align::Center, Align::Center, 0.0));
+        }
+        Graphics::Group(graphics)
+    }
+}
+
+/// A button with a centred label. Only clickable, if there is an action.
+fn button(label: &str, color: [f32;4], action: Option<Action>) -> Graphics {
+    let size = [100.0, 30.0];
+    let mut bg = Graphics::Rectangle(size[0], size[1])
+        .color(color);
+    if let Some(action) = action {
+        bg = bg.click(action);
+    }
+    let txt = Graphics::Text(14, label.to_string())
+        .cell(size, Align::Center, Align::Center);
+    Graphics::Group(vec![bg, txt])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::BlockFont;
+
+    fn game() -> Game {
+        Game::new(Session::new(test_map(), 2))
+    }
+
+    fn actions(gr: &Graphics, out: &mut Vec<Action>) {
+        match gr {
+            &Graphics::Click(ref ac, ref gr) => {
+                out.push(ac.clone());
+                actions(gr, out);
+            }
+            &Graphics::Color(_, ref gr) | &Graphics::Translate(_, ref gr)
+                | &Graphics::Scale(_, ref gr) => actions(gr, out),
+            &Graphics::Group(ref children) | &Graphics::Arrange(_, ref children) => {
+                for c in children {
+                    actions(c, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn clickable(game: &Game) -> Vec<Action> {
+        let mut out = Vec::new();
+        actions(&game.graphics([800.0, 600.0], [0.0, 0.0], &mut BlockFont), &mut out);
+        out
+    }
+
+    #[test]
+    fn place_and_undo() {
+        let mut game = game();
+        let farm = Card::named("farm").unwrap();
+        let i = game.session.deck.hand().iter().position(|c| c==&farm).unwrap() as u32;
+
+        let select = Action::Deck(farm.clone(), i);
+        assert!(clickable(&game).contains(&select));
+        game.click(select);
+        assert_eq!(game.state, State::PlaceCard(farm.clone(), i));
+        assert!(clickable(&game).contains(&Action::Field((0,1))));
+
+        game.click(Action::Field((0,1)));
+        assert_eq!(game.state, State::Def);
+        assert_eq!(game.session.map.cards.get(&(0,1)), Some(&farm));
+
+        game.undo();
+        assert_eq!(game.session.map.cards.get(&(0,1)), None);
+        game.redo();
+        assert_eq!(game.session.map.cards.get(&(0,1)), Some(&farm));
+        game.cancel();
+    }
+
+    #[test]
+    fn turns() {
+        let mut game = game();
+        assert!(game.click(Action::EndTurn).is_some());
+        assert_eq!(game.session.sim.turn, 2);
+
+        game.events = vec![Event::Famine];
+        assert!(clickable(&game).contains(&Action::CloseEvent));
+        assert!(game.click(Action::EndTurn).is_none());
+        assert_eq!(game.session.sim.turn, 2);
+        game.click(Action::CloseEvent);
+        assert!(game.events.is_empty());
+    }
+
+    #[test]
+    fn finished() {
+        let mut game = game();
+        let farm = Card::named("farm").unwrap();
+        let i = game.session.deck.hand().iter().position(|c| c==&farm).unwrap() as u32;
+        game.session.sim.game_over = true;
+        game.events = vec![Event::Famine];
+
+        game.click(Action::CloseEvent);
+        assert!(game.events.is_empty());
+        game.click(Action::Deck(farm, i));
+        assert_eq!(game.state, State::Def);
+        game.click(Action::Prioritise((0,0)));
+        assert!(game.session.map.priority.is_empty());
+        assert!(game.click(Action::EndTurn).is_none());
+        assert_eq!(game.session.sim.turn, 1);
+    }
+
+    #[test]
+    fn view() {
+        let mut game = game();
+        game.zoom_by(100.0);
+        assert_eq!(game.zoom, ZOOM_MAX);
+        game.pan([1.0, 2.0]);
+        game.pan([1.0, 2.0]);
+        assert_eq!(game.shift, [2.0, 4.0]);
+
+        let save = game.to_save();
+        let loaded = Game::from_save(save);
+        assert_eq!((loaded.zoom, loaded.shift), (ZOOM_MAX, [2.0, 4.0]));
+    }
+}
@@ -0,0 +1,128 @@
+//! Growth, starvation and housing of the cities.
+
+use super::*;
+
+/// People living in a city without any houses built.
+pub const BASE_HOUSING: u32 = 1200;
+/// People living in a single house.
+pub const HOUSE_SIZE: u32 = 100;
+/// Wood needed to build a house.
+pub const HOUSE_WOOD: u32 = 25;
+/// Houses a city can build per turn.
+const HOUSES_PER_TURN: u32 = 2;
+/// Cities start to build houses, when this fraction of the housing is used.
+const CROWDED: f64 = 0.8;
+
+impl Map {
+    /// All cities with their population.
+    pub fn cities(&self) -> Vec<(Coord, u32)> {
+        let mut v = Vec::new();
+        self.each(|x,y,tile| {
+            if let &Tile::City(p) = tile {
+                v.push(((x,y), p));
+            }
+        });
+        v
+    }
+
+    /// Number of people, that can live in the city.
+    pub fn housing(&self, coord: Coord) -> u32 {
+        BASE_HOUSING + self.houses.get(&coord).cloned().unwrap_or(0)*HOUSE_SIZE
+    }
+
+    fn set_pops(&mut self, coord: Coord, pops: u32) {
+        let i = (coord.1*self.width + coord.0) as usize;
+        if let Tile::City(ref mut p) = self.tiles[i] {
+            *p = pops;
+        }
+    }
+
+    /// Feed the people from the stockpile. If there is not enough food, the
+    /// hungry people die. Otherwise the cities grow by `growth`, as long as
+    /// there is food for the new people and housing.
+    pub fn feed_cities(&mut self, growth: f64) {
+        let pops = self.pops();
+        if pops==0 {
+            return;
+        }
+
+        let food = self.stock.food;
+        if food<pops {
+            self.stock.food = 0;
+            let fed = food as f64/pops as f64;
+            for (c, p) in self.cities() {
+                self.set_pops(c, (p as f64*fed) as u32);
+            }
+            return;
+        }
+
+        let mut surplus = food - pops;
+        for (c, p) in self.cities() {
+            let room = self.housing(c).saturating_sub(p);
+            let new = ((p as f64*growth) as u32).min(room).min(surplus);
+            surplus -= new;
+            self.set_pops(c, p+new);
+        }
+        self.stock.food = surplus;
+    }
+
+    /// Crowded cities build new houses from the wood in the stockpile.
+    pub fn build_housing(&mut self) {
+        for (c, p) in self.cities() {
+            if (p as f64) < self.housing(c) as f64*CROWDED {
+                continue;
+            }
+            let n = HOUSES_PER_TURN.min(self.stock.wood/HOUSE_WOOD);
+            if n==0 {
+                continue;
+            }
+            self.stock.wood -= n*HOUSE_WOOD;
+            *self.houses.entry(c).or_insert(0) += n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growth_needs_food() {
+        let mut map = test_map();
+        map.stock.food = 1020;
+        map.feed_cities(0.05);
+        assert_eq!(map.pops(), 1020);
+        assert_eq!(map.stock.food, 0);
+    }
+
+    #[test]
+    fn growth_needs_housing() {
+        let mut map = test_map();
+        map.set_pops((1,1), BASE_HOUSING-10);
+        map.stock.food = 10000;
+        map.feed_cities(0.05);
+        assert_eq!(map.pops(), BASE_HOUSING);
+    }
+
+    #[test]
+    fn starvation() {
+        let mut map = Map::new(2,1, vec![Tile::City(1000), Tile::City(500)]);
+        map.stock.food = 750;
+        map.feed_cities(0.05);
+        assert_eq!(map.cities(), vec![((0,0), 500), ((1,0), 250)]);
+    }
+
+    #[test]
+    fn housing() {
+        let mut map = test_map();
+        map.set_pops((1,1), 900);
+        map.stock.wood = 3*HOUSE_WOOD;
+        map.build_housing();
+        assert_eq!(map.housing((1,1)), BASE_HOUSING);
+
+        map.set_pops((1,1), BASE_HOUSING);
+        map.build_housing();
+        assert_eq!(map.housing((1,1)), BASE_HOUSING+2*HOUSE_SIZE);
+        assert_eq!(map.stock.wood, HOUSE_WOOD);
+    }
+}
@@ -0,0 +1,205 @@
+use rand::Rng;
+use super::*;
+
+/// Maximal number of cards in the hand.
+pub const HAND_SIZE: usize = 5;
+
+/// The cards of the player: a draw pile, the hand and a discard pile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deck {
+    draw_pile: Vec<Card>,
+    hand: Vec<Card>,
+    discard: Vec<Card>,
+}
+
+impl Deck {
+    /// A shuffled deck built from the deck list in `assets/cards.csv` with a
+    /// full hand.
+    pub fn new(rng: &mut GameRng) -> Deck {
+        let mut cards = Vec::new();
+        for card in Card::all() {
+            for _ in 0..card.def().copies {
+                cards.push(card.clone());
+            }
+        }
+        rng.shuffle(&mut cards);
+
+        let mut d = Deck::from_piles(cards, Vec::new(), Vec::new());
+        d.draw_cards(HAND_SIZE, rng);
+        d
+    }
+
+    pub fn from_piles(draw_pile: Vec<Card>, hand: Vec<Card>, discard: Vec<Card>) -> Deck {
+        Deck {
+            draw_pile: draw_pile,
+            hand: hand,
+            discard: discard,
+        }
+    }
+
+    pub fn hand(&self) -> &[Card] {
+        &self.hand
+    }
+
+    /// The next card drawn is the last one.
+    pub fn draw_pile(&self) -> &[Card] {
+        &self.draw_pile
+    }
+
+    pub fn discard_pile(&self) -> &[Card] {
+        &self.discard
+    }
+
+    pub fn draw(&self, width: f64, state: &State) -> Graphics {
+        let margin = 10.0;
+        let card_width = 1.2*CARD_WIDTH;
+        // The cards overlap, if there are too many for the width.
+        let dist = clamp(0.0, card_width+margin,
+                         (width-2.0*margin)/self.hand.len() as f64);
+        let mut cards = Vec::new();
+        for (i, c) in self.hand.iter().enumerate() {
+            let mut l = c.draw()
+                .scale(1.2);
+            if let &State::Def = state {
+                l = l.click(Action::Deck(c.clone(), i as u32));
+            }
+            cards.push(l);
+        }
+
+        let piles = format!("Draw pile: {}   Discard pile: {}",
+                            self.draw_pile.len(), self.discard.len());
+        Graphics::column(vec![Graphics::row(cards, dist-card_width), Text(6, piles)], 4.0)
+            .padding(margin)
+    }
+
+    /// Draw up to `n` cards, until the hand is full. The discard pile is
+    /// shuffled into the draw pile, when it runs out.
+    pub fn draw_cards(&mut self, n: usize, rng: &mut GameRng) {
+        for _ in 0..n {
+            if self.hand.len()>=HAND_SIZE {
+                break;
+            }
+            if self.draw_pile.is_empty() {
+                self.draw_pile.append(&mut self.discard);
+                rng.shuffle(&mut self.draw_pile);
+            }
+            match self.draw_pile.pop() {
+                Some(c) => self.hand.push(c),
+                None    => break,
+            }
+        }
+    }
+
+    /// Play a card from the hand and put it on the discard pile.
+    pub fn play(&mut self, index: u32) -> Card {
+        let card = self.hand.remove(index as usize);
+        self.discard.push(card.clone());
+        card
+    }
+
+    /// Take the last played card back into the hand at `index`.
+    pub fn unplay(&mut self, index: u32) {
+        if let Some(card) = self.discard.pop() {
+            self.hand.insert(index as usize, card);
+        }
+    }
+
+    /// Discard the cards, that weren't played, and draw a new hand, so cards,
+    /// that can't be played, don't stay in the hand forever.
+    /// Take a card from the hand, that stays out of the deck, like a law in
+    /// effect.
+    pub fn remove(&mut self, index: u32) -> Card {
+        self.hand.remove(index as usize)
+    }
+
+    /// Put a card back into the hand at `index`.
+    pub fn insert(&mut self, index: u32, card: Card) {
+        self.hand.insert(index as usize, card);
+    }
+
+    /// Put a card onto the discard pile, e.g. a repealed law.
+    pub fn discard(&mut self, card: Card) {
+        self.discard.push(card);
+    }
+
+    /// Take the last discarded card back.
+    pub fn undiscard(&mut self) -> Option<Card> {
+        self.discard.pop()
+    }
+
+    pub fn end_turn(&mut self, rng: &mut GameRng) {
+        self.discard.append(&mut self.hand);
+        self.draw_cards(HAND_SIZE, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(d: &Deck) -> usize {
+        d.draw_pile().len() + d.hand().len() + d.discard_pile().len()
+    }
+
+    #[test]
+    fn new_deck() {
+        let d = Deck::new(&mut GameRng::new(1));
+        let copies: u32 = Card::all().iter().map(|c| c.def().copies).sum();
+        assert_eq!(d.hand().len(), HAND_SIZE);
+        assert_eq!(total(&d), copies as usize);
+        assert!(d.discard_pile().is_empty());
+    }
+
+    #[test]
+    fn reproducible() {
+        let play = |seed| {
+            let mut rng = GameRng::new(seed);
+            let mut d = Deck::new(&mut rng);
+            d.play(2);
+            d.end_turn(&mut rng);
+            d
+        };
+        assert_eq!(play(3), play(3));
+    }
+
+    #[test]
+    fn play_and_draw() {
+        let mut rng = GameRng::new(2);
+        let mut d = Deck::new(&mut rng);
+        let n = total(&d);
+        let c = d.hand()[1].clone();
+        assert_eq!(d.play(1), c);
+        assert_eq!(d.hand().len(), HAND_SIZE-1);
+        assert_eq!(d.discard_pile(), &[c]);
+
+        d.end_turn(&mut rng);
+        assert_eq!(d.hand().len(), HAND_SIZE);
+        assert_eq!(total(&d), n);
+    }
+
+    #[test]
+    fn new_hand() {
+        let farm = Card::named("farm").unwrap();
+        let mine = Card::named("iron_mine").unwrap();
+        let mut rng = GameRng::new(2);
+        let mut d = Deck::from_piles(vec![farm.clone(); HAND_SIZE],
+                                     vec![mine.clone(); HAND_SIZE], vec![]);
+        d.end_turn(&mut rng);
+        assert_eq!(d.hand(), &vec![farm; HAND_SIZE][..]);
+        assert_eq!(d.discard_pile(), &vec![mine; HAND_SIZE][..]);
+    }
+
+    #[test]
+    fn reshuffle() {
+        let farm = Card::named("farm").unwrap();
+        let mut rng = GameRng::new(2);
+        let mut d = Deck::from_piles(vec![farm.clone()], vec![], vec![farm.clone(), farm.clone()]);
+        d.draw_cards(2, &mut rng);
+        assert_eq!(d.hand().len(), 2);
+        assert_eq!(d.draw_pile().len(), 1);
+        assert!(d.discard_pile().is_empty());
+
+        d.draw_cards(10, &mut rng);
+        assert_eq!(d.hand().len(), 3);
+    }
+}
@@ -0,0 +1,175 @@
+//! Events: like cards, but happen automatically.
+
+use rand::Rng;
+use super::*;
+
+/// Chance per turn, that an event happens, if its condition is met.
+const FAMINE: f64 = 0.5;
+const FOREST_FIRE: f64 = 0.05;
+const MINE_COLLAPSE: f64 = 0.05;
+const POPULATION_BOOM: f64 = 0.2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// There is too little food. The stockpile is lost and people die.
+    Famine,
+    /// A forest burns down, together with the card on it.
+    ForestFire(Coord),
+    /// A card on a mountain is lost.
+    MineCollapse(Coord),
+    /// A city gets a lot of new people.
+    PopulationBoom(Coord),
+}
+
+impl Event {
+    pub fn title(&self) -> &'static str {
+        use self::Event::*;
+        match self {
+            &Famine             => "Famine",
+            &ForestFire(_)      => "Forest fire",
+            &MineCollapse(_)    => "Mine collapse",
+            &PopulationBoom(_)  => "Population boom",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        use self::Event::*;
+        match self {
+            &Famine             => "The food ran out and many people starved.".to_string(),
+            &ForestFire(c)      => format!("The forest at {:?} burned down.", c),
+            &MineCollapse(c)    => format!("The mine at {:?} collapsed.", c),
+            &PopulationBoom(c)  => format!("The city at {:?} is booming.", c),
+        }
+    }
+
+    pub fn apply(&self, map: &mut Map) {
+        use self::Event::*;
+        match self {
+            &Famine => {
+                map.stock.food = 0;
+                map.each_mut(|_,_,tile| {
+                    if let &mut Tile::City(ref mut p) = tile {
+                        *p -= *p/10;
+                    }
+                });
+            }
+            &ForestFire(c) => {
+                map.demolish(c);
+                map.tiles[(c.1*map.width + c.0) as usize] = Tile::Farmland;
+            }
+            &MineCollapse(c) => {
+                map.demolish(c);
+            }
+            &PopulationBoom(c) => {
+                if let Tile::City(ref mut p) = map.tiles[(c.1*map.width + c.0) as usize] {
+                    *p += *p/5;
+                }
+            }
+        }
+    }
+
+    /// Popup shown to the player.
+    pub fn draw(&self) -> Graphics {
+        let bg = Rectangle(260.0, 110.0)
+            .color([0.9, 0.9, 0.8, 1.0]);
+        let title = Text(16, self.title().to_string())
+            .translate([10.0, 25.0]);
+        let txt = Graphics::text_block(10, self.description(), 240.0, Align::Start)
+            .translate([10.0, 50.0]);
+
+        let button = Rectangle(60.0, 24.0)
+            .color([0.6, 0.6, 0.5, 1.0])
+            .click(Action::CloseEvent);
+        let ok = Text(12, "OK".to_string())
+            .cell([60.0, 24.0], Align::Center, Align::Center);
+        let button = Group(vec![button, ok])
+            .translate([190.0, 76.0]);
+
+        Group(vec![bg, title, txt, button])
+    }
+}
+
+/// Decide which events happen this turn.
+pub fn roll_events(map: &Map, rng: &mut GameRng) -> Vec<Event> {
+    let mut events = Vec::new();
+    let coords = |f: &dyn Fn(Coord, &Tile) -> bool| {
+        let mut v = Vec::new();
+        map.each(|x,y,tile| if f((x,y), tile) { v.push((x,y)); });
+        v
+    };
+
+    let forests = coords(&|_, t| t==&Tile::Forrest);
+    let mines = coords(&|c, t| map.cards.contains_key(&c) && match t {
+        &Tile::Coal | &Tile::Iron | &Tile::Mountain => true,
+        _ => false,
+    });
+    let cities = coords(&|_, t| match t {
+        &Tile::City(p)  => p>0,
+        _               => false,
+    });
+
+    let pops = map.pops();
+    if map.stock.food<pops/2 && rng.gen::<f64>()<FAMINE {
+        events.push(Event::Famine);
+    }
+    if !forests.is_empty() && rng.gen::<f64>()<FOREST_FIRE {
+        events.push(Event::ForestFire(*rng.choose(&forests).unwrap()));
+    }
+    if !mines.is_empty() && rng.gen::<f64>()<MINE_COLLAPSE {
+        events.push(Event::MineCollapse(*rng.choose(&mines).unwrap()));
+    }
+    if !cities.is_empty() && map.stock.food>=pops && rng.gen::<f64>()<POPULATION_BOOM {
+        events.push(Event::PopulationBoom(*rng.choose(&cities).unwrap()));
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects() {
+        let mut map = test_map();
+        map.stock.food = 500;
+        Event::Famine.apply(&mut map);
+        assert_eq!(map.stock.food, 0);
+        assert_eq!(map.pops(), 900);
+
+        Event::PopulationBoom((1,1)).apply(&mut map);
+        assert_eq!(map.pops(), 1080);
+
+        Event::ForestFire((0,0)).apply(&mut map);
+        assert_eq!(map.tile((0,0)), &Tile::Farmland);
+        assert!(map.cards.is_empty());
+    }
+
+    #[test]
+    fn conditions() {
+        let mut map = Map::new(2,1, vec![Tile::City(1000), Tile::Farmland]);
+        map.stock.food = 2000;
+        let mut rng = GameRng::new(0);
+        for _ in 0..100 {
+            for e in roll_events(&map, &mut rng) {
+                assert_eq!(e, Event::PopulationBoom((0,0)));
+            }
+        }
+
+        map.stock.food = 0;
+        let famines = (0..100)
+            .filter(|_| roll_events(&map, &mut rng).contains(&Event::Famine))
+            .count();
+        assert!(famines>0);
+    }
+
+    #[test]
+    fn reproducible() {
+        let map = test_map();
+        let roll = |seed| {
+            let mut rng = GameRng::new(seed);
+            (0..20).map(|_| roll_events(&map, &mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(roll(5), roll(5));
+    }
+}
@@ -0,0 +1,152 @@
+//! Victory goals and the score at the end of a game.
+
+use super::*;
+
+/// Points per good produced in the last turn.
+const PRODUCTION_POINTS: u32 = 2;
+/// Points per turn survived, if the game was lost.
+const SURVIVAL_POINTS: u32 = 10;
+/// Points per turn, that a victory took less than `PAR_TURNS`.
+const SPEED_POINTS: u32 = 100;
+const PAR_TURNS: u32 = 50;
+
+/// Something, the player has to achieve to win.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// Reach a total population.
+    Population(u32),
+    /// Play the number of turns.
+    Survive(u32),
+    /// Have the resources in the stockpile.
+    Stockpile(Resources),
+}
+
+impl Goal {
+    /// Goals of maps, that don't define their own.
+    pub fn defaults() -> Vec<Goal> {
+        vec![Goal::Population(3000)]
+    }
+
+    /// Has the goal been reached after playing `turns` turns?
+    pub fn reached(&self, map: &Map, turns: u32) -> bool {
+        match self {
+            &Goal::Population(p)    => map.pops()>=p,
+            &Goal::Survive(t)       => turns>=t,
+            &Goal::Stockpile(ref r) => map.stock.contains(r),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            &Goal::Population(p)    => format!("Population {}", p),
+            &Goal::Survive(t)       => format!("Survive {} turns", t),
+            &Goal::Stockpile(ref r) => format!("Stockpile {}", r),
+        }
+    }
+}
+
+impl Map {
+    /// The goals of the map, all of them are needed to win.
+    pub fn victory_goals(&self) -> Vec<Goal> {
+        if self.goals.is_empty() {
+            Goal::defaults()
+        } else {
+            self.goals.clone()
+        }
+    }
+
+    pub fn goals_reached(&self, turns: u32) -> bool {
+        self.victory_goals().iter().all(|g| g.reached(self, turns))
+    }
+}
+
+/// The result of a finished game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub victory: bool,
+    pub turns: u32,
+    pub pops: u32,
+    pub production: Resources,
+    pub score: u32,
+}
+
+impl Summary {
+    pub fn new(map: &Map, sim: &Simulation) -> Summary {
+        let turns = sim.turn.saturating_sub(1);
+        let production = map.production();
+        let goods = production.food + production.wood + production.coal
+            + production.iron + production.stone;
+        let time = if sim.victory {
+            SPEED_POINTS*PAR_TURNS.saturating_sub(turns)
+        } else {
+            SURVIVAL_POINTS*turns
+        };
+        Summary {
+            victory: sim.victory,
+            turns: turns,
+            pops: map.pops(),
+            production: production,
+            score: map.pops() + PRODUCTION_POINTS*goods + time,
+        }
+    }
+
+    /// Screen shown at the end of the game. `rank` is the place in the high
+    /// score table, if the game made it in there.
+    pub fn draw(&self, scores: &HighScores, rank: Option<usize>) -> Graphics {
+        let height = 130.0 + 16.0*scores.entries.len() as f64;
+        let bg = Rectangle(300.0, height)
+            .color([0.9, 0.9, 0.8, 1.0]);
+        let title = if self.victory { "Victory!" } else { "Game over" };
+        let mut gr = vec![
+            bg,
+            Text(18, title.to_string()).translate([10.0, 28.0]),
+            Text(10, format!("Turns {}   Population {}", self.turns, self.pops))
+                .translate([10.0, 50.0]),
+            Text(10, format!("Production: {}", self.production)).translate([10.0, 66.0]),
+            Text(14, format!("Score {}", self.score)).translate([10.0, 88.0]),
+            Text(12, "High scores".to_string()).translate([10.0, 114.0]),
+        ];
+        for (i, e) in scores.entries.iter().enumerate() {
+            let mark = if rank==Some(i) { ">" } else { " " };
+            let line = format!("{} {}. {}  {} turns  seed {}", mark, i+1, e.score, e.turns, e.seed);
+            gr.push(Text(10, line).translate([10.0, 130.0 + 16.0*i as f64]));
+        }
+        Group(gr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::Tile::*;
+
+    #[test]
+    fn goals() {
+        let mut map = test_map();
+        assert_eq!(map.victory_goals(), Goal::defaults());
+        assert!(!map.goals_reached(10));
+
+        map.goals = vec![Goal::Survive(5),
+                         Goal::Stockpile(Resources { food: 100, ..Resources::new() })];
+        assert!(!map.goals_reached(5));
+        map.stock.food = 100;
+        assert!(!map.goals_reached(4));
+        assert!(map.goals_reached(5));
+    }
+
+    #[test]
+    fn score() {
+        let map = Map::new(2,1, vec![City(1000), Forrest]);
+        let mut sim = Simulation { turn: 11, game_over: true, victory: false };
+        let s = Summary::new(&map, &sim);
+        assert_eq!((s.turns, s.score), (10, 1000 + 10*SURVIVAL_POINTS));
+
+        sim.victory = true;
+        let s = Summary::new(&map, &sim);
+        assert_eq!(s.score, 1000 + 40*SPEED_POINTS);
+
+        // Turn 0 can come from a save game.
+        sim.turn = 0;
+        assert_eq!(Summary::new(&map, &sim).turns, 0);
+    }
+}
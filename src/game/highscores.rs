@@ -0,0 +1,136 @@
+//! Local table of the best scores.
+//!
+//! One line per entry, sorted by score:
+//!
+//! ```text
+//! # score turns seed result
+//! 4520 17 42 won
+//! 1300 30 7 lost
+//! ```
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use super::*;
+
+/// Number of entries kept in the table.
+pub const MAX_HIGH_SCORES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HighScore {
+    pub score: u32,
+    pub turns: u32,
+    pub seed: u64,
+    pub victory: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Load the table. A missing file is an empty table.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<HighScores, DefError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(HighScores::default());
+        }
+        let s = read_file(path)?;
+        HighScores::parse(&path.display().to_string(), &s)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut f = File::create(path)?;
+        f.write_all(self.to_table_string().as_bytes())
+    }
+
+    /// Parse a table. `file` is only used for error messages.
+    pub fn parse(file: &str, data: &str) -> Result<HighScores, DefError> {
+        let mut scores = HighScores::default();
+        for (i, line) in data.lines().enumerate() {
+            let error = |msg: String| DefError {
+                file: file.to_string(),
+                line: i as u64+1,
+                column: 0,
+                msg: msg,
+            };
+            let words: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            if words.len()!=4 {
+                return Err(error("expected `<score> <turns> <seed> won|lost`".to_string()));
+            }
+            let invalid = |w: &str| error(format!("invalid number `{}`", w));
+            scores.entries.push(HighScore {
+                score: words[0].parse().map_err(|_| invalid(words[0]))?,
+                turns: words[1].parse().map_err(|_| invalid(words[1]))?,
+                seed: words[2].parse().map_err(|_| invalid(words[2]))?,
+                victory: match words[3] {
+                    "won"   => true,
+                    "lost"  => false,
+                    w       => return Err(error(format!("invalid result `{}`", w))),
+                },
+            });
+        }
+        scores.entries.sort_by(|a, b| b.score.cmp(&a.score));
+        scores.entries.truncate(MAX_HIGH_SCORES);
+        Ok(scores)
+    }
+
+    pub fn to_table_string(&self) -> String {
+        let mut s = "# score turns seed result\n".to_string();
+        for e in self.entries.iter() {
+            let result = if e.victory { "won" } else { "lost" };
+            s += &format!("{} {} {} {}\n", e.score, e.turns, e.seed, result);
+        }
+        s
+    }
+
+    /// Add an entry. Returns its place in the table, if it's good enough.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self.entries.iter()
+            .position(|e| e.score<entry.score)
+            .unwrap_or(self.entries.len());
+        if rank>=MAX_HIGH_SCORES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32) -> HighScore {
+        HighScore { score: score, turns: 10, seed: 1, victory: score>1000 }
+    }
+
+    #[test]
+    fn insert() {
+        let mut scores = HighScores::default();
+        for i in 0..MAX_HIGH_SCORES as u32 {
+            assert_eq!(scores.insert(entry(100*i)), Some(0));
+        }
+        assert_eq!(scores.insert(entry(0)), None);
+        assert_eq!(scores.insert(entry(450)), Some(5));
+        assert_eq!(scores.entries.len(), MAX_HIGH_SCORES);
+        assert_eq!(scores.entries.last(), Some(&entry(100)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut scores = HighScores::default();
+        scores.insert(entry(2000));
+        scores.insert(entry(500));
+        let s = scores.to_table_string();
+        assert_eq!(HighScores::parse("test", &s), Ok(scores));
+        assert_eq!(HighScores::parse("test", "1 2 3 draw").unwrap_err().line, 1);
+        assert_eq!(HighScores::parse("test", "\n1 x 3 won").unwrap_err().line, 2);
+        assert!(HighScores::parse("test", "4294967296 2 3 won").is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Undo and redo of the actions of the player within a turn.
+
+use super::*;
+
+/// A change of the map and deck made by the player. Contains everything
+/// needed to take it back.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Play the card with the index in the hand onto a tile.
+    PlaceCard { coord: Coord, card: Card, index: u32 },
+    /// Play the card with the index in the hand as a law.
+    EnactLaw { card: Card, index: u32 },
+    RepealLaw { index: u32, law: Card },
+    Prioritise { coord: Coord, before: Vec<Coord> },
+    BuildRoad(Coord),
+    Demolish { coord: Coord, card: Card, level: u32, priority: Vec<Coord> },
+}
+
+impl Command {
+    pub fn repeal_law(map: &Map, index: u32) -> Option<Command> {
+        map.laws.get(index as usize).map(|law| Command::RepealLaw {
+            index: index,
+            law: law.clone(),
+        })
+    }
+
+    pub fn prioritise(map: &Map, coord: Coord) -> Command {
+        Command::Prioritise {
+            coord: coord,
+            before: map.priority.clone(),
+        }
+    }
+
+    pub fn demolish(map: &Map, coord: Coord) -> Option<Command> {
+        map.cards.get(&coord).map(|card| Command::Demolish {
+            coord: coord,
+            card: card.clone(),
+            level: map.level(coord),
+            priority: map.priority.clone(),
+        })
+    }
+
+    /// Carry out the command. Returns false and changes nothing, if that's
+    /// not possible.
+    pub fn apply(&self, map: &mut Map, deck: &mut Deck) -> bool {
+        use self::Command::*;
+        match self {
+            &PlaceCard { coord, ref card, index } => {
+                if deck.hand().get(index as usize)!=Some(card) || !map.place_card(coord, card.clone()) {
+                    return false;
+                }
+                deck.play(index);
+            }
+            &EnactLaw { ref card, index } => {
+                if deck.hand().get(index as usize)!=Some(card) || !map.can_enact(card) {
+                    return false;
+                }
+                map.enact_law(deck.remove(index));
+            }
+            &RepealLaw { index, ref law } => {
+                if map.laws.get(index as usize)!=Some(law) {
+                    return false;
+                }
+                deck.discard(map.repeal_law(index));
+            }
+            &Prioritise { coord, .. } => {
+                if !map.cards.contains_key(&coord) {
+                    return false;
+                }
+                map.prioritise(coord);
+            }
+            &BuildRoad(coord) => {
+                if !map.can_build_road(coord) {
+                    return false;
+                }
+                map.build_road(coord);
+            }
+            &Demolish { coord, .. } => {
+                if map.demolish(coord).is_none() {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Take back the command. Must be called right after `apply`, or after
+    /// the commands applied later have been undone.
+    pub fn undo(&self, map: &mut Map, deck: &mut Deck) {
+        use self::Command::*;
+        match self {
+            &PlaceCard { coord, index, .. } => {
+                match map.level(coord) {
+                    1 => { map.cards.remove(&coord); }
+                    2 => { map.levels.remove(&coord); }
+                    l => { map.levels.insert(coord, l-1); }
+                }
+                deck.unplay(index);
+            }
+            &EnactLaw { index, .. } => {
+                if let Some(law) = map.laws.pop() {
+                    deck.insert(index, law);
+                }
+            }
+            &RepealLaw { index, .. } => {
+                if let Some(law) = deck.undiscard() {
+                    map.laws.insert(index as usize, law);
+                }
+            }
+            &Prioritise { ref before, .. } => {
+                map.priority = before.clone();
+            }
+            &BuildRoad(coord) => {
+                map.roads.remove(&coord);
+                map.stock += ROAD_COST;
+            }
+            &Demolish { coord, ref card, level, ref priority } => {
+                map.cards.insert(coord, card.clone());
+                if level>1 {
+                    map.levels.insert(coord, level);
+                }
+                map.priority = priority.clone();
+            }
+        }
+    }
+}
+
+/// Commands done and undone in the current turn.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    done: Vec<Command>,
+    undone: Vec<Command>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Apply a new command. Commands undone before can't be redone anymore.
+    pub fn execute(&mut self, cmd: Command, map: &mut Map, deck: &mut Deck) -> bool {
+        if !cmd.apply(map, deck) {
+            return false;
+        }
+        self.done.push(cmd);
+        self.undone.clear();
+        true
+    }
+
+    pub fn undo(&mut self, map: &mut Map, deck: &mut Deck) -> bool {
+        match self.done.pop() {
+            Some(cmd) => {
+                cmd.undo(map, deck);
+                self.undone.push(cmd);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, map: &mut Map, deck: &mut Deck) -> bool {
+        match self.undone.pop() {
+            Some(cmd) => {
+                let ok = cmd.apply(map, deck);
+                debug_assert!(ok);
+                self.done.push(cmd);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget everything, e.g. at the end of a turn.
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm() -> Card { Card::named("farm").unwrap() }
+    fn guilds() -> Card { Card::named("guilds").unwrap() }
+
+    fn setup() -> (Map, Deck) {
+        let mut map = test_map();
+        map.stock.wood = 100;
+        let deck = Deck::from_piles(vec![], vec![farm(), guilds(), farm()], vec![]);
+        (map, deck)
+    }
+
+    #[test]
+    fn undo_redo() {
+        let (mut map, mut deck) = setup();
+        let (map0, deck0) = (map.clone(), deck.clone());
+        let mut h = History::new();
+
+        let place = Command::PlaceCard { coord: (0,1), card: farm(), index: 2 };
+        assert!(h.execute(place.clone(), &mut map, &mut deck));
+        assert!(!h.execute(place, &mut map, &mut deck));
+        let upgrade = Command::PlaceCard { coord: (0,1), card: farm(), index: 0 };
+        assert!(h.execute(upgrade, &mut map, &mut deck));
+        assert!(h.execute(Command::EnactLaw { card: guilds(), index: 0 }, &mut map, &mut deck));
+        assert!(h.execute(Command::BuildRoad((0,2)), &mut map, &mut deck));
+        let demolish = Command::demolish(&map, (0,0)).unwrap();
+        assert!(h.execute(demolish, &mut map, &mut deck));
+        let repeal = Command::repeal_law(&map, 0).unwrap();
+        assert!(h.execute(repeal, &mut map, &mut deck));
+        let (map1, deck1) = (map.clone(), deck.clone());
+
+        while h.undo(&mut map, &mut deck) {}
+        assert_eq!(map.cards, map0.cards);
+        assert_eq!(map.levels, map0.levels);
+        assert_eq!(map.laws, map0.laws);
+        assert_eq!(map.roads, map0.roads);
+        assert_eq!(map.stock, map0.stock);
+        assert_eq!(deck, deck0);
+
+        while h.redo(&mut map, &mut deck) {}
+        assert_eq!(map.cards, map1.cards);
+        assert_eq!(map.levels, map1.levels);
+        assert_eq!(map.laws, map1.laws);
+        assert_eq!(map.roads, map1.roads);
+        assert_eq!(deck, deck1);
+    }
+
+    #[test]
+    fn laws_stay_out_of_the_deck() {
+        let (mut map, mut deck) = setup();
+        let mut h = History::new();
+        assert!(h.execute(Command::EnactLaw { card: guilds(), index: 1 }, &mut map, &mut deck));
+        assert_eq!(deck.hand(), &[farm(), farm()]);
+        assert!(deck.discard_pile().is_empty());
+
+        let repeal = Command::repeal_law(&map, 0).unwrap();
+        assert!(h.execute(repeal, &mut map, &mut deck));
+        assert_eq!(deck.discard_pile(), &[guilds()]);
+
+        assert!(h.undo(&mut map, &mut deck));
+        assert_eq!(map.laws, vec![guilds()]);
+        assert!(deck.discard_pile().is_empty());
+        assert!(h.undo(&mut map, &mut deck));
+        assert_eq!(deck.hand(), &[farm(), guilds(), farm()]);
+    }
+
+    #[test]
+    fn new_command_drops_redo() {
+        let (mut map, mut deck) = setup();
+        let mut h = History::new();
+        h.execute(Command::BuildRoad((0,2)), &mut map, &mut deck);
+        assert!(h.undo(&mut map, &mut deck));
+        h.execute(Command::prioritise(&map, (0,0)), &mut map, &mut deck);
+        assert!(!h.redo(&mut map, &mut deck));
+        assert!(h.undo(&mut map, &mut deck));
+        assert!(map.priority.is_empty());
+        assert!(!h.undo(&mut map, &mut deck));
+    }
+}
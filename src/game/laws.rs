@@ -0,0 +1,147 @@
+//! Laws: cards, that are not placed on the map, but change the rules as long
+//! as they are in effect.
+
+use super::*;
+
+/// Maximal number of laws in effect at the same time.
+pub const MAX_LAWS: usize = 3;
+
+/// Modifiers of a law in percent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LawEffects {
+    /// Change of the workers needed by cards.
+    pub workers: i32,
+    /// Additional growth of the cities per turn.
+    pub growth: i32,
+    /// Change of the administration cost.
+    pub admin: i32,
+}
+
+impl LawEffects {
+    /// Parse a list like `workers=-20 growth=2`.
+    pub fn parse(s: &str) -> Result<LawEffects, String> {
+        let mut e = LawEffects::default();
+        for word in s.split_whitespace() {
+            let mut kv = word.splitn(2, '=');
+            let key = kv.next().unwrap();
+            let value = kv.next()
+                .and_then(|v| v.parse::<i32>().ok())
+                .ok_or_else(|| format!("invalid effect `{}`", word))?;
+            match key {
+                "workers"   => e.workers += value,
+                "growth"    => e.growth += value,
+                "admin"     => e.admin += value,
+                _           => return Err(format!("unknown effect `{}`", key)),
+            }
+        }
+        Ok(e)
+    }
+
+    /// Apply a percentual change, never resulting in less than zero.
+    pub fn scale(value: u32, percent: i32) -> u32 {
+        let v = value as i64*(100+percent as i64)/100;
+        clamp(0, v, u32::max_value() as i64) as u32
+    }
+}
+
+impl Map {
+    /// The combined effects of all laws in effect.
+    pub fn law_effects(&self) -> LawEffects {
+        let mut e = LawEffects::default();
+        for law in self.laws.iter() {
+            let l = law.def().effects;
+            e.workers += l.workers;
+            e.growth += l.growth;
+            e.admin += l.admin;
+        }
+        e
+    }
+
+    pub fn can_enact(&self, card: &Card) -> bool {
+        card.is_law() && self.laws.len()<MAX_LAWS && !self.laws.contains(card)
+    }
+
+    pub fn enact_law(&mut self, card: Card) {
+        assert!(self.can_enact(&card));
+        self.laws.push(card);
+    }
+
+    pub fn repeal_law(&mut self, index: u32) -> Card {
+        self.laws.remove(index as usize)
+    }
+
+    /// Panel listing the laws in effect.
+    pub fn draw_laws(&self, state: &State) -> Graphics {
+        let width = 120.0;
+        let mut bg = Rectangle(width, 40.0 + 30.0*MAX_LAWS as f64)
+            .color([0.7, 0.7, 0.8, 1.0]);
+        if let &State::PlaceCard(ref card, _) = state {
+            if self.can_enact(card) {
+                bg = bg.click(Action::EnactLaw);
+            }
+        }
+        let title = Text(14, "Laws".to_string())
+            .translate([10.0, 20.0]);
+
+        let mut gr = vec![bg, title];
+        for (i, law) in self.laws.iter().enumerate() {
+            let name = Text(10, law.title())
+                .translate([10.0, 18.0]);
+            let mut repeal = Rectangle(40.0, 20.0)
+                .color([0.8, 0.5, 0.5, 1.0]);
+            if let &State::Def = state {
+                repeal = repeal.click(Action::RepealLaw(i as u32));
+            }
+            let repeal = Group(vec![repeal, Text(8, "Repeal".to_string()).translate([5.0, 14.0])])
+                .translate([width-50.0, 3.0]);
+
+            let row = Group(vec![
+                Rectangle(width-10.0, 26.0).color(law.color()),
+                name,
+                repeal,
+            ]);
+            gr.push(row.translate([5.0, 30.0 + 30.0*i as f64]));
+        }
+        Group(gr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn law(name: &str) -> Card {
+        Card::named(name).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(LawEffects::parse("workers=-20 growth=2"),
+                   Ok(LawEffects { workers: -20, growth: 2, admin: 0 }));
+        assert!(LawEffects::parse("workers=x").is_err());
+        assert!(LawEffects::parse("taxes=10").is_err());
+    }
+
+    #[test]
+    fn scale() {
+        assert_eq!(LawEffects::scale(100, -20), 80);
+        assert_eq!(LawEffects::scale(100, -200), 0);
+        assert_eq!(LawEffects::scale(100, 50), 150);
+    }
+
+    #[test]
+    fn enact_and_repeal() {
+        let mut map = test_map();
+        let before = map.nec_pops();
+        assert!(!map.can_enact(&Card::named("farm").unwrap()));
+
+        map.enact_law(law("guilds"));
+        assert!(!map.can_enact(&law("guilds")));
+        assert!(map.nec_pops()<before);
+
+        map.enact_law(law("bureaucracy"));
+        assert_eq!(map.law_effects().admin, -50);
+        assert_eq!(map.repeal_law(0), law("guilds"));
+        assert_eq!(map.laws, vec![law("bureaucracy")]);
+    }
+}
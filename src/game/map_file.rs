@@ -0,0 +1,404 @@
+//! Text format for maps.
+//!
+//! ```text
+//! # comment
+//! size 2 3
+//! T ^
+//! . C
+//! . c
+//! city 1 1 1000 2
+//! road 0 1
+//! card 0 0 lumber 2
+//! law guilds
+//! priority 0 0
+//! goal population 3000
+//! goal survive 20
+//! goal stock 0 500 0 0 100
+//! ```
+//!
+//! The header is followed by one line per row of the map, with one tile symbol
+//! (see `assets/tiles.csv`) per column. Spaces between the symbols are ignored.
+//! Afterwards the population and optionally the number of houses of the
+//! cities, the roads, the already placed cards with their level, the laws in
+//! effect, the cards, that get their workers first, and the goals needed to
+//! win are listed. Roads are read before all other lines, because they decide
+//! where cards can be placed, and priorities after all other lines, because
+//! they need the cards.
+//! The stock goal lists food, wood, coal, iron and stone.
+
+use std::path::Path;
+use std::fmt::Write;
+use super::*;
+
+/// Population of cities without a `city` line.
+pub const DEFAULT_CITY_POP: u32 = 1000;
+
+struct Line<'a> {
+    file: &'a str,
+    line: u64,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn error(&self, column: u64, msg: String) -> DefError {
+        DefError {
+            file: self.file.to_string(),
+            line: self.line,
+            column: column,
+            msg: msg,
+        }
+    }
+
+    /// Words of the line with their columns.
+    fn words(&self) -> Vec<(u64, &'a str)> {
+        let text = self.text;
+        let mut words = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices().chain(Some((text.len(), ' '))) {
+            match (start, c.is_whitespace()) {
+                (None, false)       => start = Some(i),
+                (Some(s), true)     => {
+                    words.push((text[..s].chars().count() as u64+1, &text[s..i]));
+                    start = None;
+                }
+                _                   => {}
+            }
+        }
+        words
+    }
+
+    /// Parse a directive with a fixed number of arguments.
+    fn args(&self, n: usize) -> Result<Vec<(u64, &'a str)>, DefError> {
+        let words = self.words();
+        if words.len()!=n+1 {
+            return Err(self.error(1, format!("`{}` expects {} arguments", words[0].1, n)));
+        }
+        Ok(words[1..].to_vec())
+    }
+
+    fn number(&self, arg: (u64, &str)) -> Result<u32, DefError> {
+        arg.1.parse().map_err(|_| self.error(arg.0, format!("invalid number `{}`", arg.1)))
+    }
+
+    fn coord(&self, map: &Map, x: (u64, &str), y: (u64, &str)) -> Result<Coord, DefError> {
+        let c = (self.number(x)?, self.number(y)?);
+        if c.0>=map.width {
+            Err(self.error(x.0, format!("x coordinate {} outside of the map", c.0)))
+        } else if c.1>=map.height {
+            Err(self.error(y.0, format!("y coordinate {} outside of the map", c.1)))
+        } else {
+            Ok(c)
+        }
+    }
+}
+
+impl Map {
+    /// Load a map file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Map, DefError> {
+        let path = path.as_ref();
+        let s = read_file(path)?;
+        Map::parse(&path.display().to_string(), &s)
+    }
+
+    /// Parse a map. `file` is only used for error messages.
+    pub fn parse(file: &str, data: &str) -> Result<Map, DefError> {
+        let reg = registry();
+        let mut lines = data.lines()
+            .enumerate()
+            .map(|(i, l)| Line {
+                file: file,
+                line: i as u64+1,
+                text: l.split('#').next().unwrap(),
+            })
+            .filter(|l| !l.text.trim().is_empty());
+
+        let eof = |line: u64| DefError {
+            file: file.to_string(),
+            line: line,
+            column: 0,
+            msg: "unexpected end of file".to_string(),
+        };
+
+        let header = lines.next().ok_or_else(|| eof(1))?;
+        let (width, height) = match header.words().first() {
+            Some(&(_, "size")) => {
+                let args = header.args(2)?;
+                (header.number(args[0])?, header.number(args[1])?)
+            }
+            _ => return Err(header.error(1, "expected `size <width> <height>`".to_string())),
+        };
+        if width==0 || height==0 {
+            return Err(header.error(1, "empty map".to_string()));
+        }
+
+        let mut tiles = Vec::new();
+        let mut last = header.line;
+        for y in 0..height {
+            let row = lines.next().ok_or_else(|| eof(last+1))?;
+            last = row.line;
+            let mut x = 0;
+            for (col, c) in row.text.chars().enumerate() {
+                if c.is_whitespace() {
+                    continue;
+                }
+                let col = col as u64+1;
+                if x==width {
+                    return Err(row.error(col, format!("row longer than {} tiles", width)));
+                }
+                let tile = reg.tile_by_symbol(c)
+                    .and_then(|e| Tile::from_name(&e.name))
+                    .ok_or_else(|| row.error(col, format!("unknown tile symbol `{}`", c)))?;
+                let tile = match tile {
+                    Tile::City(_)   => Tile::City(DEFAULT_CITY_POP),
+                    t               => t,
+                };
+                tiles.push(tile);
+                x += 1;
+            }
+            if x<width {
+                return Err(row.error(0, format!("row shorter than {} tiles", width)));
+            }
+        }
+
+        let mut map = Map::new(width, height, tiles);
+
+        let mut lines: Vec<_> = lines.collect();
+        lines.sort_by_key(|l| match l.words()[0].1 {
+            "road"      => 0,
+            "priority"  => 2,
+            _           => 1,
+        });
+        for line in lines {
+            let words = line.words();
+            match words[0].1 {
+                "city" => {
+                    let args = if words.len()==5 { line.args(4)? } else { line.args(3)? };
+                    let coord = line.coord(&map, args[0], args[1])?;
+                    let pop = line.number(args[2])?;
+                    if let Some(&houses) = args.get(3) {
+                        let houses = line.number(houses)?;
+                        map.houses.insert(coord, houses);
+                    }
+                    let i = (coord.1*width + coord.0) as usize;
+                    match map.tiles[i] {
+                        Tile::City(ref mut p)   => *p = pop,
+                        _                       => return Err(line.error(args[0].0,
+                                format!("no city at {:?}", coord))),
+                    }
+                }
+                "card" => {
+                    let args = if words.len()==5 { line.args(4)? } else { line.args(3)? };
+                    let coord = line.coord(&map, args[0], args[1])?;
+                    let card = Card::named(args[2].1)
+                        .ok_or_else(|| line.error(args[2].0,
+                                format!("unknown card `{}`", args[2].1)))?;
+                    if map.cards.contains_key(&coord) || !map.place_card(coord, card) {
+                        return Err(line.error(args[2].0,
+                                format!("card `{}` can't be placed at {:?}", args[2].1, coord)));
+                    }
+                    if let Some(&level) = args.get(3) {
+                        let n = line.number(level)?;
+                        if n==0 || n>MAX_LEVEL {
+                            return Err(line.error(level.0, format!("invalid level {}", n)));
+                        }
+                        map.levels.insert(coord, n);
+                    }
+                }
+                "law" => {
+                    let args = line.args(1)?;
+                    let card = Card::named(args[0].1)
+                        .ok_or_else(|| line.error(args[0].0,
+                                format!("unknown card `{}`", args[0].1)))?;
+                    if !map.can_enact(&card) {
+                        return Err(line.error(args[0].0,
+                                format!("law `{}` can't be enacted", args[0].1)));
+                    }
+                    map.enact_law(card);
+                }
+                "priority" => {
+                    let args = line.args(2)?;
+                    let coord = line.coord(&map, args[0], args[1])?;
+                    if !map.cards.contains_key(&coord) {
+                        return Err(line.error(args[0].0, format!("no card at {:?}", coord)));
+                    }
+                    map.priority.push(coord);
+                }
+                "road" => {
+                    let args = line.args(2)?;
+                    let coord = line.coord(&map, args[0], args[1])?;
+                    if map.has_road(coord) {
+                        return Err(line.error(args[0].0, format!("road at {:?} already exists", coord)));
+                    }
+                    map.roads.insert(coord);
+                }
+                "goal" => {
+                    let goal = match words.get(1) {
+                        Some(&(_, "population")) => {
+                            Goal::Population(line.number(line.args(2)?[1])?)
+                        }
+                        Some(&(_, "survive")) => {
+                            Goal::Survive(line.number(line.args(2)?[1])?)
+                        }
+                        Some(&(_, "stock")) => {
+                            let args = line.args(6)?;
+                            Goal::Stockpile(Resources {
+                                food: line.number(args[1])?,
+                                wood: line.number(args[2])?,
+                                coal: line.number(args[3])?,
+                                iron: line.number(args[4])?,
+                                stone: line.number(args[5])?,
+                            })
+                        }
+                        Some(&(col, w)) => {
+                            return Err(line.error(col, format!("unknown goal `{}`", w)));
+                        }
+                        None => return Err(line.error(1, "missing goal".to_string())),
+                    };
+                    map.goals.push(goal);
+                }
+                w => return Err(line.error(1, format!("unknown directive `{}`", w))),
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Write the map in the format read by `parse`.
+    pub fn to_map_string(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "size {} {}", self.width, self.height).unwrap();
+        for y in 0..self.height {
+            let row: Vec<String> = (0..self.width)
+                .map(|x| self.tile((x,y)).def().symbol.to_string())
+                .collect();
+            writeln!(s, "{}", row.join(" ")).unwrap();
+        }
+        self.each(|x,y,tile| {
+            if let &Tile::City(p) = tile {
+                match self.houses.get(&(x,y)) {
+                    Some(h) => writeln!(s, "city {} {} {} {}", x, y, p, h).unwrap(),
+                    None    => writeln!(s, "city {} {} {}", x, y, p).unwrap(),
+                }
+            }
+        });
+        let mut roads: Vec<_> = self.roads.iter().collect();
+        roads.sort();
+        for &(x,y) in roads {
+            writeln!(s, "road {} {}", x, y).unwrap();
+        }
+        let mut cards: Vec<_> = self.cards.iter().collect();
+        cards.sort();
+        for (&(x,y), card) in cards {
+            match self.level((x,y)) {
+                1       => writeln!(s, "card {} {} {}", x, y, card.def().name).unwrap(),
+                level   => writeln!(s, "card {} {} {} {}", x, y, card.def().name, level).unwrap(),
+            }
+        }
+        for law in self.laws.iter() {
+            writeln!(s, "law {}", law.def().name).unwrap();
+        }
+        for &(x,y) in self.priority.iter().filter(|c| self.cards.contains_key(c)) {
+            writeln!(s, "priority {} {}", x, y).unwrap();
+        }
+        for goal in self.goals.iter() {
+            match goal {
+                &Goal::Population(p)    => writeln!(s, "goal population {}", p).unwrap(),
+                &Goal::Survive(t)       => writeln!(s, "goal survive {}", t).unwrap(),
+                &Goal::Stockpile(ref r) => writeln!(s, "goal stock {} {} {} {} {}",
+                                                    r.food, r.wood, r.coal, r.iron, r.stone).unwrap(),
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MAP: &'static str = "
+        # The test map
+        size 2 3
+        T ^
+        . C
+        . c
+        city 1 1 1000
+        card 0 0 lumber
+    ";
+
+    #[test]
+    fn parse() {
+        let map = Map::parse("test", TEST_MAP).unwrap();
+        let test = test_map();
+        assert_eq!(map.tiles, test.tiles);
+        assert_eq!(map.cards, test.cards);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut map = test_map();
+        map.place_card((0,1), Card::named("farm").unwrap());
+        map.place_card((0,1), Card::named("farm").unwrap());
+        map.enact_law(Card::named("guilds").unwrap());
+        map.houses.insert((1,1), 3);
+        map.prioritise((0,1));
+        map.prioritise((0,0));
+        map.roads.insert((0,1));
+        map.roads.insert((1,2));
+        map.goals = vec![Goal::Population(2000), Goal::Survive(10),
+                         Goal::Stockpile(Resources { wood: 100, ..Resources::new() })];
+        let s = map.to_map_string();
+        let map2 = Map::parse("test", &s).unwrap();
+        assert_eq!(map.tiles, map2.tiles);
+        assert_eq!(map.cards, map2.cards);
+        assert_eq!(map.laws, map2.laws);
+        assert_eq!(map.houses, map2.houses);
+        assert_eq!(map.priority, map2.priority);
+        assert_eq!(map.roads, map2.roads);
+        assert_eq!(map.levels, map2.levels);
+        assert_eq!(map.goals, map2.goals);
+    }
+
+    #[test]
+    fn card_on_road() {
+        use self::Tile::*;
+        let mut map = Map::new(5,1, vec![City(1000), Farmland, Farmland, Farmland, Farmland]);
+        map.roads.insert((1,0));
+        map.roads.insert((2,0));
+        assert!(map.place_card((3,0), Card::named("farm").unwrap()));
+        let map2 = Map::parse("test", &map.to_map_string()).unwrap();
+        assert_eq!(map.cards, map2.cards);
+
+        // The order of the lines doesn't matter.
+        let s = "size 5 1\nC . . . .\npriority 3 0\ncard 3 0 farm\nroad 1 0\nroad 2 0";
+        let map2 = Map::parse("test", s).unwrap();
+        assert_eq!(map2.cards, map.cards);
+        assert_eq!(map2.priority, vec![(3,0)]);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |s: &str| {
+            let e = Map::parse("test", s).unwrap_err();
+            (e.line, e.column)
+        };
+        assert_eq!(err("size 2"), (1, 1));
+        assert_eq!(err("size 2 x"), (1, 8));
+        assert_eq!(err("size 2 1\nT"), (2, 0));
+        assert_eq!(err("size 2 1\nT T T"), (2, 5));
+        assert_eq!(err("size 2 1\nT x"), (2, 3));
+        assert_eq!(err("size 2 2\nT T"), (3, 0));
+        assert_eq!(err("size 2 1\nT C\ncity 0 0 10"), (3, 6));
+        assert_eq!(err("size 2 1\nT C\ncity 2 0 10"), (3, 6));
+        assert_eq!(err("size 2 1\nT C\ncard 0 0 farm"), (3, 10));
+        assert_eq!(err("size 2 1\nT C\ncard 0 0 castle"), (3, 10));
+        assert_eq!(err("size 2 1\nT C\nriver 0 0"), (3, 1));
+        assert_eq!(err("size 2 1\nT C\nlaw farm"), (3, 5));
+        assert_eq!(err("size 2 1\nT C\npriority 0 0"), (3, 10));
+        assert_eq!(err("size 2 1\nT C\nroad 1 0"), (3, 6));
+        assert_eq!(err("size 2 1\nT C\ncard 0 0 lumber 4"), (3, 17));
+        assert_eq!(err("size 2 1\nT C\ngoal wealth 5"), (3, 6));
+        assert_eq!(err("size 2 1\nT C\ngoal stock 5"), (3, 1));
+        assert_eq!(err("size 2 1\nT C\ncard 0 0 lumber\ncard 0 0 lumber"), (4, 10));
+    }
+}
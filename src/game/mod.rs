@@ -0,0 +1,543 @@
+#![allow(unused_variables, unused_imports)]
+use std::collections::{HashMap, HashSet};
+use super::Graphics;
+use ::Graphics::*;
+use ::graphics::Align;
+use rand::{self, Rand, Rng};
+use rand::distributions::{Range, Sample};
+use std::fmt;
+
+mod simulation;
+mod resources;
+mod registry;
+mod map_file;
+mod save;
+mod rng;
+mod deck;
+mod events;
+mod laws;
+mod cities;
+mod workers;
+mod placement;
+mod roads;
+mod upgrades;
+mod history;
+mod replay;
+mod goals;
+mod highscores;
+pub mod worldgen;
+pub use self::simulation::*;
+pub use self::resources::*;
+pub use self::registry::*;
+pub use self::map_file::*;
+pub use self::save::*;
+pub use self::rng::*;
+pub use self::deck::*;
+pub use self::events::*;
+pub use self::laws::*;
+pub use self::cities::*;
+pub use self::workers::*;
+pub use self::placement::*;
+pub use self::roads::*;
+pub use self::upgrades::*;
+pub use self::history::*;
+pub use self::replay::*;
+pub use self::goals::*;
+pub use self::highscores::*;
+
+#[derive(Clone,Debug, PartialEq, Eq)]
+pub enum State {
+    Def,
+    PlaceCard(Card, u32),
+    BuildRoad,
+    Demolish,
+}
+
+#[derive(Clone,Debug, PartialEq, Eq)]
+pub enum Action {
+    Field((u32,u32)),
+    Deck(Card, u32),
+    EndTurn,
+    CloseEvent,
+    EnactLaw,
+    RepealLaw(u32),
+    Prioritise((u32,u32)),
+    StartRoad,
+    Road((u32,u32)),
+    StartDemolish,
+    Demolish((u32,u32)),
+}
+
+const CARD_WIDTH: f64 = 40.0;
+const CARD_HEIGHT: f64 = 60.0;
+
+/// A card. The properties are defined in `assets/cards.csv`.
+#[derive(Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct Card(pub u32);
+
+impl fmt::Debug for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.def().name)
+    }
+}
+
+impl Card {
+    /// Find a card by its name.
+    pub fn named(name: &str) -> Option<Card> {
+        registry().card(name)
+    }
+
+    /// All the different cards.
+    pub fn all() -> Vec<Card> {
+        (0..registry().cards.len() as u32).map(Card).collect()
+    }
+
+    pub fn def(&self) -> Def<CardDef> {
+        Def::card(self)
+    }
+
+    pub fn color(&self) -> [f32;4] {
+        self.def().color
+    }
+
+    pub fn title(&self) -> String {
+        self.def().title.clone()
+    }
+
+    /// Workers necessary to run the card.
+    pub fn workers(&self) -> u32 {
+        self.def().workers
+    }
+
+    pub fn is_law(&self) -> bool {
+        self.def().kind==CardKind::Law
+    }
+
+    /// Can the card be placed on the tile?
+    pub fn valid_on(&self, tile: &Tile) -> bool {
+        self.def().tiles.iter().any(|e| e==tile.name())
+    }
+
+    /// Goods produced per turn, when fully staffed.
+    pub fn production(&self) -> Resources {
+        self.def().production
+    }
+
+    pub fn draw(&self) -> Graphics {
+        let bg = Rectangle(CARD_WIDTH, CARD_HEIGHT)
+            .color(self.color());
+
+        let txt = Graphics::text_block(6, self.title(), CARD_WIDTH-4.0, Align::Center)
+            .padding(2.0);
+
+        // Titles with long words stick out, but the card keeps its size in
+        // layouts.
+        Group(vec![bg,txt])
+            .cell([CARD_WIDTH, CARD_HEIGHT], Align::Start, Align::Start)
+    }
+}
+
+type Coord = (u32,u32);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Forrest,
+    Farmland,
+    Mountain,
+    Coal,
+    Iron,
+    City(u32),
+}
+
+impl Tile {
+    /// Names of all tiles, as used in the definition files.
+    pub fn names() -> &'static [&'static str] {
+        &["Forrest", "Farmland", "Mountain", "Coal", "Iron", "City"]
+    }
+
+    pub fn name(&self) -> &'static str {
+        use self::Tile::*;
+        match self {
+            &Forrest    => "Forrest",
+            &Farmland   => "Farmland",
+            &Mountain   => "Mountain",
+            &Coal       => "Coal",
+            &Iron       => "Iron",
+            &City(_)    => "City",
+        }
+    }
+
+    /// The tile with the given name. Cities are empty.
+    pub fn from_name(name: &str) -> Option<Tile> {
+        use self::Tile::*;
+        Some(match name {
+            "Forrest"   => Forrest,
+            "Farmland"  => Farmland,
+            "Mountain"  => Mountain,
+            "Coal"      => Coal,
+            "Iron"      => Iron,
+            "City"      => City(0),
+            _           => return None,
+        })
+    }
+
+    pub fn def(&self) -> Def<TileDef> {
+        Def::tile(self.name())
+            .expect("tile definitions are checked on load")
+    }
+
+    pub fn color(&self) -> [f32;4] {
+        self.def().color
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            &Tile::City(p)  => format!("{} {}", self.def().label, p),
+            _               => self.def().label.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Map {
+    pub tiles: Vec<Tile>,
+    pub width: u32,
+    pub height: u32,
+    pub cards: HashMap<Coord,Card>,
+    pub stock: Resources,
+    /// Laws in effect.
+    pub laws: Vec<Card>,
+    /// Houses built in the cities.
+    pub houses: HashMap<Coord,u32>,
+    /// Cards, that get their workers first.
+    pub priority: Vec<Coord>,
+    pub roads: HashSet<Coord>,
+    /// Levels of the upgraded cards.
+    pub levels: HashMap<Coord,u32>,
+    /// Goals needed to win, see `victory_goals`.
+    pub goals: Vec<Goal>,
+}
+
+impl Map {
+    pub fn new(width: u32, height: u32, tiles: Vec<Tile>) -> Map {
+        assert_eq!(tiles.len() as u32, width*height);
+        Map {
+            width: width,
+            height: height,
+            tiles: tiles,
+            cards: HashMap::new(),
+            stock: Resources::new(),
+            laws: Vec::new(),
+            houses: HashMap::new(),
+            priority: Vec::new(),
+            roads: HashSet::new(),
+            levels: HashMap::new(),
+            goals: Vec::new(),
+        }
+    }
+
+    /// Is the tile on the map?
+    pub fn contains(&self, coord: Coord) -> bool {
+        coord.0<self.width && coord.1<self.height
+    }
+
+    pub fn tile(&self, coord: Coord) -> &Tile {
+        &self.tiles[(coord.1*self.width + coord.0) as usize]
+    }
+
+    pub fn matches(&self, coord: (u32,u32), card: &Card) -> bool {
+        self.matches_with(coord, card, &self.road_network())
+    }
+
+    /// Like `matches` with the `road_network`, when many tiles are checked.
+    pub fn matches_with(&self, coord: Coord, card: &Card, network: &HashSet<Coord>) -> bool {
+        if !self.contains(coord) {
+            return false;
+        }
+        match self.cards.get(&coord) {
+            Some(_) => self.can_upgrade(coord, card),
+            None    => card.valid_on(self.tile(coord)) && self.in_range_with(coord, card, network),
+        }
+    }
+
+    /// Total population.
+    pub fn pops(&self) -> u32 {
+        use self::Tile::*;
+        self.tiles.iter().map(|e| 
+            match e {
+                &City(p)    => p,
+                _           => 0,
+            }
+        ).sum()
+    }
+
+    /// Neccessary population.
+    pub fn nec_pops(&self) -> u32 {
+        let effects = self.law_effects();
+        let workers:u32 = self.cards.keys()
+            .map(|&c| self.card_workers(c))
+            .sum();
+        let admin = self.pops() / 10;
+        workers + LawEffects::scale(admin, effects.admin)
+    }
+
+    /// Goods delivered to the cities by all cards on matching tiles,
+    /// depending on their level, how well they are staffed, their neighbours
+    /// and the distance to the next city.
+    pub fn production(&self) -> Resources {
+        let staffing = self.staffing();
+        let transport = self.transport_costs();
+        let mut res = Resources::new();
+        for (&coord, card) in self.cards.iter() {
+            if card.valid_on(self.tile(coord)) {
+                let bonus = (100 + self.bonus(coord, card)).max(0) as f64/100.0;
+                let delivery = delivered(transport[(coord.1*self.width + coord.0) as usize]);
+                let factor = staffing[&coord]*bonus*delivery*self.level_production(coord);
+                res += card.production()*factor;
+            }
+        }
+        res
+    }
+
+    pub fn build_graphics(&self, state: &State) -> Graphics {
+        let staffing = self.staffing();
+        let network = self.road_network();
+        let mut group = Vec::new();
+        self.each(|x,y,tile| {
+            let tile_size = 100.0;
+            let tile_box = [tile_size, tile_size];
+            let mut bg = Rectangle(tile_size,tile_size)
+                .color(tile.color());
+
+            let mut highlight = None;
+            if let &State::PlaceCard(ref card,i) = state {
+                if self.matches_with((x,y), card, &network) {
+                    bg = bg.click(Action::Field((x,y)));
+                    let mut h = vec![Rectangle(tile_size, tile_size)
+                        .color([1.0, 1.0, 1.0, 0.3])];
+                    let bonus = self.bonus((x,y), card);
+                    if bonus!=0 {
+                        h.push(Text(10, format!("{:+}%", bonus))
+                            .padding(5.0)
+                            .cell(tile_box, Align::Start, Align::End));
+                    }
+                    highlight = Some(Group(h));
+                }
+            }
+            if let &State::BuildRoad = state {
+                if self.can_build_road((x,y)) {
+                    bg = bg.click(Action::Road((x,y)));
+                    highlight = Some(Rectangle(tile_size, tile_size)
+                        .color([1.0, 1.0, 1.0, 0.3]));
+                }
+            }
+
+            let mut labels = vec![Text(12, tile.text())];
+            if let &Tile::City(_) = tile {
+                labels.push(Text(8, format!("Housing {}", self.housing((x,y)))));
+            }
+
+            let mut gr = vec![bg];
+            if self.roads.contains(&(x,y)) {
+                gr.push(self.draw_road((x,y), tile_size));
+            }
+            gr.push(Graphics::column(labels, 2.0).padding(4.0));
+            if let Some(card) = self.cards.get(&(x,y)) {
+                let card_box = [CARD_WIDTH, CARD_HEIGHT];
+                let mut bottom = Vec::new();
+                let level = self.level((x,y));
+                if level>1 {
+                    bottom.push(Text(8, format!("Lv {}", level)).padding(2.0));
+                }
+                let staffed = staffing[&(x,y)];
+                if staffed<1.0 {
+                    let bar = Rectangle(CARD_WIDTH, 12.0)
+                        .color([0.9, 0.2, 0.2, 1.0]);
+                    let txt = Text(8, format!("{}%", (staffed*100.0) as u32))
+                        .cell([CARD_WIDTH, 12.0], Align::Center, Align::Center);
+                    bottom.push(Group(vec![bar, txt]));
+                }
+                let mut c = vec![card.draw(),
+                                 Graphics::column(bottom, 0.0)
+                                     .cell(card_box, Align::Start, Align::End)];
+                if self.priority.first()==Some(&(x,y)) {
+                    c.push(Text(10, "!".to_string())
+                        .padding(2.0)
+                        .cell(card_box, Align::End, Align::Start));
+                }
+                let mut c = Group(c);
+                match state {
+                    &State::Def         => c = c.click(Action::Prioritise((x,y))),
+                    &State::Demolish    => c = c.click(Action::Demolish((x,y))),
+                    _                   => {}
+                }
+
+                gr.push(c.padding(5.0).cell(tile_box, Align::Center, Align::End));
+            }
+            gr.extend(highlight);
+
+            group.push(Group(gr));
+        });
+        Graphics::grid(group, self.width as usize, 0.0)
+    }
+
+    pub fn each<F>(&self, mut f: F) -> ()
+    where F: FnMut(u32, u32, &Tile) -> ()
+    {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                f(x, y, &self.tiles[(y*self.width + x) as usize]);
+            }
+        }
+    }
+
+    pub fn each_mut<F>(&mut self, mut f: F) -> ()
+    where F: FnMut(u32, u32, &mut Tile) -> ()
+    {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                f(x, y, &mut self.tiles[(y*self.width + x) as usize]);
+            }
+        }
+    }
+
+    /// Get all the places, where you can put a card. Cards can be placed on
+    /// the same card to upgrade it.
+    pub fn card_options(&self) -> Vec<(Coord,Card)> {
+        let mut places = Vec::new();
+        let all = Card::all();
+        let network = self.road_network();
+
+        self.each(|x,y,tile| {
+            for card in all.iter().filter(|c| self.matches_with((x,y), c, &network)) {
+                places.push(((x,y),card.clone()));
+            }
+        });
+
+        places
+    }
+
+    /// Place a card on the map or upgrade the same card. Returns false, if
+    /// that's not possible.
+    pub fn place_card(&mut self, coord: Coord, card: Card) -> bool {
+        if !self.matches(coord, &card) {
+            return false;
+        }
+        if self.cards.contains_key(&coord) {
+            *self.levels.entry(coord).or_insert(1) += 1;
+        } else {
+            self.cards.insert(coord, card);
+        }
+        true
+    }
+}
+
+pub fn clamp<T: PartialOrd>(min: T, val: T, max: T) -> T {
+    if val<min {
+        min
+    } else if val>max {
+        max
+    } else {
+        val
+    }
+}
+
+/// Distance between two tiles, diagonal steps count as one.
+pub fn distance(a: Coord, b: Coord) -> u32 {
+    let dx = (a.0 as i64 - b.0 as i64).abs();
+    let dy = (a.1 as i64 - b.1 as i64).abs();
+    dx.max(dy) as u32
+}
+
+pub fn test_map() -> Map {
+    use self::Tile::*;
+
+    let mut map = Map::new(2,3,
+        vec![Forrest,Mountain,
+                Farmland,City(1000),
+                Farmland,Coal]);
+    map.place_card((0,0),Card::named("lumber").unwrap());
+    map
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::Tile::*;
+
+    fn farm() -> Card { Card::named("farm").unwrap() }
+    fn lumber() -> Card { Card::named("lumber").unwrap() }
+    fn quarry() -> Card { Card::named("quarry").unwrap() }
+    fn coal_mine() -> Card { Card::named("coal_mine").unwrap() }
+
+    #[test]
+    fn test_test_map() {
+        test_map();
+    }
+
+    #[test]
+    fn map_pops() {
+        assert_eq!(test_map().pops(), 1000);
+    }
+
+    #[test]
+    fn card_options() {
+        let map = test_map();
+        assert_eq!(map.card_options(), vec![
+                ((0,0),lumber()),
+                ((1,0),quarry()),
+                ((0,1),farm()),
+                ((0,2),farm()),
+                ((1,2),coal_mine()),
+        ])
+    }
+
+    #[test]
+    fn card_placement() {
+        let mut map = test_map();
+        assert!(map.place_card((0,1),farm()));
+    }
+
+    #[test]
+    fn production() {
+        let mut map = test_map();
+        map.place_card((0,1),farm());
+        // The lumbermill gets a bonus from the mountain next to it.
+        assert_eq!(map.production(), Resources { food: 400, wood: 62, ..Resources::new() });
+
+        let mut map = Map::new(3,1, vec![Forrest, City(150), Farmland]);
+        map.place_card((0,0),lumber());
+        map.place_card((2,0),farm());
+        assert_eq!(map.production(), Resources { food: 140, wood: 50, ..Resources::new() });
+    }
+
+    #[test]
+    fn mines() {
+        let mut map = test_map();
+        let before = map.nec_pops();
+        map.place_card((1,0),quarry());
+        map.place_card((1,2),coal_mine());
+        assert_eq!(map.nec_pops(), before+250);
+        assert_eq!(map.production(), Resources { wood: 62, coal: 40, stone: 40, ..Resources::new() });
+        assert!(!map.matches((1,1), &quarry()));
+    }
+
+    #[test]
+    fn test_clamp() {
+        assert_eq!(clamp(0.0, 10.0, 100.0), 10.0);
+        assert_eq!(clamp(0.0, 100.0, 10.0), 10.0);
+        assert_eq!(clamp(10.0, 0.0, 100.0), 10.0);
+    }
+
+
+    #[test]
+    fn card_placement_fail() {
+        let mut map = test_map();
+        assert!(!map.place_card((1,0),lumber()));
+        assert!(!map.place_card((0,0),farm()));
+        assert!(!map.place_card((2,0),farm()));
+        assert!(!map.place_card((0,3),farm()));
+        assert_eq!(map.cards.len(), 1);
+    }
+}
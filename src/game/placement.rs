@@ -0,0 +1,72 @@
+//! Rules for placing cards, that depend on the surroundings. The values are
+//! defined in the `range` and `bonus` columns of `assets/cards.csv`.
+
+use std::collections::HashSet;
+use super::*;
+
+impl Map {
+    /// Tiles sharing an edge with the tile.
+    pub fn neighbours(&self, coord: Coord) -> Vec<Coord> {
+        let (x, y) = coord;
+        let mut v = Vec::new();
+        if y>0 { v.push((x, y-1)); }
+        if x>0 { v.push((x-1, y)); }
+        if x+1<self.width { v.push((x+1, y)); }
+        if y+1<self.height { v.push((x, y+1)); }
+        v
+    }
+
+    /// Is a city close enough to the tile or is it connected by a road, to
+    /// place the card?
+    pub fn in_range(&self, coord: Coord, card: &Card) -> bool {
+        self.in_range_with(coord, card, &self.road_network())
+    }
+
+    /// Like `in_range` with the `road_network`, when many tiles are checked.
+    pub fn in_range_with(&self, coord: Coord, card: &Card, network: &HashSet<Coord>) -> bool {
+        let range = card.def().range;
+        range==0
+            || self.cities().iter().any(|&(c, _)| distance(c, coord)<=range)
+            || self.connected_with(coord, network)
+    }
+
+    /// Production bonus in percent of the card on the tile.
+    pub fn bonus(&self, coord: Coord, card: &Card) -> i32 {
+        let def = card.def();
+        self.neighbours(coord).into_iter()
+            .map(|c| {
+                let name = self.tile(c).name();
+                def.bonus.iter()
+                    .filter(|&&(ref t, _)| t==name)
+                    .map(|&(_, b)| b)
+                    .sum::<i32>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::Tile::*;
+
+    #[test]
+    fn range() {
+        let farm = Card::named("farm").unwrap();
+        let map = Map::new(4,1, vec![City(100), Farmland, Farmland, Farmland]);
+        assert!(map.in_range((2,0), &farm));
+        assert!(!map.in_range((3,0), &farm));
+        assert!(!map.matches((3,0), &farm));
+    }
+
+    #[test]
+    fn bonus() {
+        let lumber = Card::named("lumber").unwrap();
+        let map = Map::new(3,2, vec![Mountain, Forrest, Mountain,
+                                     City(100), Forrest, Forrest]);
+        assert_eq!(map.neighbours((0,0)), vec![(1,0), (0,1)]);
+        assert_eq!(map.bonus((1,0), &lumber), 50);
+        assert_eq!(map.bonus((2,1), &lumber), 25);
+        assert_eq!(map.bonus((1,1), &lumber), 0);
+    }
+}
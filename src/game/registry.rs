@@ -0,0 +1,422 @@
+extern crate csv;
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use super::*;
+
+/// Definitions compiled into the binary. Used until `set_registry` is called,
+/// e.g. in tests.
+const CARDS_CSV: &'static str = include_str!("../../assets/cards.csv");
+const TILES_CSV: &'static str = include_str!("../../assets/tiles.csv");
+
+const CARD_COLUMNS: &'static [&'static str] = &["name", "title", "color", "workers",
+    "tiles", "food", "wood", "coal", "iron", "stone", "deck", "kind", "effects",
+    "range", "bonus"];
+const TILE_COLUMNS: &'static [&'static str] = &["name", "label", "color", "symbol"];
+
+lazy_static! {
+    static ref REGISTRY: RwLock<Arc<Registry>> =
+        RwLock::new(Arc::new(Registry::builtin()));
+}
+
+/// The currently active definitions.
+pub fn registry() -> Arc<Registry> {
+    REGISTRY.read().unwrap().clone()
+}
+
+/// Replace the active definitions. Must be called before any `Card` is created.
+pub fn set_registry(reg: Registry) {
+    *REGISTRY.write().unwrap() = Arc::new(reg);
+}
+
+/// A definition borrowed from the registry. Keeps the registry alive, even if
+/// it is replaced in the meantime.
+pub struct Def<T: 'static> {
+    registry: Arc<Registry>,
+    index: usize,
+    get: fn(&Registry, usize) -> &T,
+}
+
+impl Def<CardDef> {
+    pub fn card(card: &Card) -> Def<CardDef> {
+        Def {
+            registry: registry(),
+            index: card.0 as usize,
+            get: |r, i| &r.cards[i],
+        }
+    }
+}
+
+impl Def<TileDef> {
+    pub fn tile(name: &str) -> Option<Def<TileDef>> {
+        let registry = registry();
+        let index = registry.tiles.iter().position(|e| e.name==name);
+        index.map(|i| Def {
+            registry: registry,
+            index: i,
+            get: |r, i| &r.tiles[i],
+        })
+    }
+}
+
+impl<T> Deref for Def<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        (self.get)(&self.registry, self.index)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardKind {
+    /// Placed on a tile of the map.
+    Field,
+    /// Placed in the laws area, see `laws`.
+    Law,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CardDef {
+    pub name: String,
+    pub title: String,
+    pub color: [f32;4],
+    pub workers: u32,
+    /// Names of the tiles, this card can be placed on.
+    pub tiles: Vec<String>,
+    pub production: Resources,
+    /// Copies in the starting deck.
+    pub copies: u32,
+    pub kind: CardKind,
+    /// Only used by laws.
+    pub effects: LawEffects,
+    /// Maximal distance to a city, 0 if the card can be placed anywhere.
+    pub range: u32,
+    /// Production bonus in percent for each neighbouring tile of the type.
+    pub bonus: Vec<(String, i32)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileDef {
+    pub name: String,
+    pub label: String,
+    pub color: [f32;4],
+    /// Character used in map files.
+    pub symbol: char,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    pub cards: Vec<CardDef>,
+    pub tiles: Vec<TileDef>,
+}
+
+/// Error in a file the game reads: definitions, maps, save games, replays and
+/// high scores. Line and column start at 1, a column of 0 refers to the whole
+/// line, a line of 0 to the whole file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefError {
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+    pub msg: String,
+}
+
+impl fmt::Display for DefError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.msg)
+    }
+}
+
+/// Read a whole file into a string.
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<String, DefError> {
+    let path = path.as_ref();
+    let mut s = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut s))
+        .map_err(|e| DefError {
+            file: path.display().to_string(),
+            line: 0,
+            column: 0,
+            msg: e.to_string(),
+        })?;
+    Ok(s)
+}
+
+impl Registry {
+    /// Load `cards.csv` and `tiles.csv` from a directory.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Registry, DefError> {
+        let dir = dir.as_ref();
+        let cards = read_file(dir.join("cards.csv"))?;
+        let tiles = read_file(dir.join("tiles.csv"))?;
+        Registry::parse(&cards, &tiles)
+    }
+
+    /// The definitions shipped with the game.
+    pub fn builtin() -> Registry {
+        Registry::parse(CARDS_CSV, TILES_CSV)
+            .expect("invalid builtin definitions")
+    }
+
+    pub fn parse(cards: &str, tiles: &str) -> Result<Registry, DefError> {
+        let mut reg = Registry::default();
+
+        for row in records("tiles.csv", tiles, TILE_COLUMNS)? {
+            let def = TileDef {
+                name: row.name(0, reg.tiles.iter().map(|e| &e.name))?,
+                label: row.get(1).to_string(),
+                color: row.color(2)?,
+                symbol: row.symbol(3, reg.tiles.iter().map(|e| e.symbol))?,
+            };
+            if !Tile::names().contains(&&*def.name) {
+                return Err(row.error(1, format!("unknown tile `{}`", def.name)));
+            }
+            reg.tiles.push(def);
+        }
+        for name in Tile::names() {
+            if reg.tile(name).is_none() {
+                return Err(DefError {
+                    file: "tiles.csv".to_string(),
+                    line: 0,
+                    column: 0,
+                    msg: format!("missing tile `{}`", name),
+                });
+            }
+        }
+
+        for row in records("cards.csv", cards, CARD_COLUMNS)? {
+            let mut tiles = Vec::new();
+            for t in row.get(4).split_whitespace() {
+                if reg.tile(t).is_none() {
+                    return Err(row.error(5, format!("unknown tile `{}`", t)));
+                }
+                tiles.push(t.to_string());
+            }
+
+            let mut bonus = Vec::new();
+            for word in row.get(14).split_whitespace() {
+                let mut kv = word.splitn(2, '=');
+                let tile = kv.next().unwrap();
+                if reg.tile(tile).is_none() {
+                    return Err(row.error(15, format!("unknown tile `{}`", tile)));
+                }
+                let value = kv.next()
+                    .and_then(|v| v.parse::<i32>().ok())
+                    .ok_or_else(|| row.error(15, format!("invalid bonus `{}`", word)))?;
+                bonus.push((tile.to_string(), value));
+            }
+
+            let def = CardDef {
+                name: row.name(0, reg.cards.iter().map(|e| &e.name))?,
+                title: row.get(1).to_string(),
+                color: row.color(2)?,
+                workers: row.parse(3)?,
+                tiles: tiles,
+                production: Resources {
+                    food: row.parse(5)?,
+                    wood: row.parse(6)?,
+                    coal: row.parse(7)?,
+                    iron: row.parse(8)?,
+                    stone: row.parse(9)?,
+                },
+                copies: row.parse(10)?,
+                kind: match row.get(11) {
+                    "field" => CardKind::Field,
+                    "law"   => CardKind::Law,
+                    k       => return Err(row.error(12, format!("unknown kind `{}`", k))),
+                },
+                effects: LawEffects::parse(row.get(12))
+                    .map_err(|e| row.error(13, e))?,
+                range: row.parse(13)?,
+                bonus: bonus,
+            };
+            if def.kind==CardKind::Law && !def.tiles.is_empty() {
+                return Err(row.error(5, "laws can't be placed on tiles".to_string()));
+            }
+            reg.cards.push(def);
+        }
+
+        Ok(reg)
+    }
+
+    pub fn tile(&self, name: &str) -> Option<&TileDef> {
+        self.tiles.iter().find(|e| e.name==name)
+    }
+
+    pub fn tile_by_symbol(&self, symbol: char) -> Option<&TileDef> {
+        self.tiles.iter().find(|e| e.symbol==symbol)
+    }
+
+    pub fn card(&self, name: &str) -> Option<Card> {
+        self.cards.iter()
+            .position(|e| e.name==name)
+            .map(|i| Card(i as u32))
+    }
+}
+
+/// A single record of a definition file.
+struct Row {
+    file: &'static str,
+    line: u64,
+    fields: Vec<String>,
+}
+
+impl Row {
+    fn get(&self, col: usize) -> &str {
+        self.fields.get(col).map(|e| e.trim()).unwrap_or("")
+    }
+
+    fn error(&self, column: u64, msg: String) -> DefError {
+        DefError {
+            file: self.file.to_string(),
+            line: self.line,
+            column: column,
+            msg: msg,
+        }
+    }
+
+    fn parse<T: FromStr>(&self, col: usize) -> Result<T, DefError> {
+        let s = self.get(col);
+        s.parse().map_err(|_| self.error(col as u64+1, format!("invalid number `{}`", s)))
+    }
+
+    /// A non-empty identifier, that is not in `existing`.
+    fn name<'a, I>(&self, col: usize, mut existing: I) -> Result<String, DefError>
+    where I: Iterator<Item=&'a String>
+    {
+        let s = self.get(col);
+        if s.is_empty() {
+            Err(self.error(col as u64+1, "empty name".to_string()))
+        } else if existing.any(|e| e==s) {
+            Err(self.error(col as u64+1, format!("duplicate name `{}`", s)))
+        } else {
+            Ok(s.to_string())
+        }
+    }
+
+    /// A single, printable character, that is not in `existing`.
+    fn symbol<I>(&self, col: usize, mut existing: I) -> Result<char, DefError>
+    where I: Iterator<Item=char>
+    {
+        let s = self.get(col);
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c=='#' || c.is_whitespace() || c.is_control() =>
+                Err(self.error(col as u64+1, format!("reserved symbol `{}`", c))),
+            (Some(c), None) if existing.any(|e| e==c) =>
+                Err(self.error(col as u64+1, format!("duplicate symbol `{}`", c))),
+            (Some(c), None) => Ok(c),
+            _ => Err(self.error(col as u64+1, format!("invalid symbol `{}`", s))),
+        }
+    }
+
+    /// A color in the form `#rrggbb`.
+    fn color(&self, col: usize) -> Result<[f32;4], DefError> {
+        let s = self.get(col);
+        let err = || self.error(col as u64+1, format!("invalid color `{}`", s));
+        if s.len()!=7 || !s.starts_with('#') || !s[1..].chars().all(|c| c.is_digit(16)) {
+            return Err(err());
+        }
+        let mut c = [1.0;4];
+        for i in 0..3 {
+            let v = u8::from_str_radix(&s[1+2*i..3+2*i], 16).map_err(|_| err())?;
+            c[i] = v as f32/255.0;
+        }
+        Ok(c)
+    }
+}
+
+/// Read all records of a csv file and check the header.
+fn records(file: &'static str, data: &str, columns: &[&str]) -> Result<Vec<Row>, DefError> {
+    let mut rdr = csv::Reader::from_string(data).has_headers(false);
+    let mut rows = Vec::new();
+
+    for (i, rec) in rdr.records().enumerate() {
+        let line = i as u64+1;
+        let fields = rec.map_err(|e| match e {
+            csv::Error::Parse(e) => DefError {
+                file: file.to_string(),
+                line: e.record,
+                column: e.field,
+                msg: e.err.to_string(),
+            },
+            e => DefError {
+                file: file.to_string(),
+                line: line,
+                column: 0,
+                msg: e.to_string(),
+            },
+        })?;
+        let row = Row { file: file, line: line, fields: fields };
+
+        if i==0 {
+            for (col, &name) in columns.iter().enumerate() {
+                if row.get(col)!=name {
+                    return Err(row.error(col as u64+1,
+                        format!("expected column `{}`, found `{}`", name, row.get(col))));
+                }
+            }
+        } else {
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin() {
+        let reg = Registry::builtin();
+        assert_eq!(reg.card("farm"), Some(Card(0)));
+        assert_eq!(reg.cards[0].title, "Farm");
+        assert_eq!(reg.cards[0].color, [0.2, 0.8, 0.4, 1.0]);
+        assert_eq!(reg.cards[0].tiles, vec!["Farmland".to_string()]);
+        assert_eq!(reg.cards[1].bonus, vec![("Mountain".to_string(), 25)]);
+        assert_eq!(reg.tile("City").unwrap().label, "City");
+    }
+
+    #[test]
+    fn errors() {
+        let err = |cards: &str| Registry::parse(cards, TILES_CSV).unwrap_err();
+        let header = "name,title,color,workers,tiles,food,wood,coal,iron,stone,deck,kind,effects,\
+                      range,bonus\n";
+
+        let e = err(&format!("{}farm,Farm,#33cc66,many,Farmland,1,0,0,0,0,1,field,,0,", header));
+        assert_eq!((e.line, e.column), (2, 4));
+
+        let e = err(&format!("{}farm,Farm,#33cc66,1,Farmland,1,0,0,0,0,1,field,,0,\n\
+                              mill,Mill,#3c6,1,Forrest,1,0,0,0,0,1,field,,0,", header));
+        assert_eq!((e.line, e.column), (3, 3));
+
+        let e = err(&format!("{}farm,Farm,#33cc66,1,Farmland Lake,1,0,0,0,0,1,field,,0,", header));
+        assert_eq!((e.line, e.column), (2, 5));
+
+        let e = err(&format!("{}farm,Farm,#33cc66,1,Farmland,1,0", header));
+        assert_eq!(e.line, 2);
+
+        let e = err(&format!("{}tax,Tax,#33cc66,0,,0,0,0,0,0,1,law,taxes=5,0,", header));
+        assert_eq!((e.line, e.column), (2, 13));
+
+        let e = err(&format!("{}tax,Tax,#33cc66,0,Farmland,0,0,0,0,0,1,law,,0,", header));
+        assert_eq!((e.line, e.column), (2, 5));
+
+        let e = err(&format!("{}mill,Mill,#cc9966,1,Forrest,0,1,0,0,0,1,field,,x,", header));
+        assert_eq!((e.line, e.column), (2, 14));
+
+        let e = err(&format!("{}mill,Mill,#cc9966,1,Forrest,0,1,0,0,0,1,field,,0,Lake=5", header));
+        assert_eq!((e.line, e.column), (2, 15));
+
+        let e = err(&format!("{}mill,Mill,#cc9966,1,Forrest,0,1,0,0,0,1,field,,0,Mountain", header));
+        assert_eq!((e.line, e.column), (2, 15));
+
+        let e = err("name,title,colour");
+        assert_eq!((e.line, e.column), (1, 3));
+    }
+}
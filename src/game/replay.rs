@@ -0,0 +1,369 @@
+//! Recording of the input of the player and headless playback.
+//!
+//! A replay starts with the number of lines of the initial save game, followed
+//! by the save game in the format of `save` and one line per input, prefixed
+//! with the turn it was made in:
+//!
+//! ```text
+//! ld38 replay 1
+//! start 21
+//! ld38 save 4
+//! ...
+//! 1 place 0 1 2
+//! 1 end
+//! 2 undo
+//! ```
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use super::*;
+
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Input of the player, that changes the game.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    /// Place the card with the index in the hand on a tile.
+    Place(Coord, u32),
+    /// Enact the card with the index in the hand as law.
+    Law(u32),
+    Repeal(u32),
+    Prioritise(Coord),
+    Road(Coord),
+    Demolish(Coord),
+    Undo,
+    Redo,
+    EndTurn,
+}
+
+impl Input {
+    pub fn to_line(&self) -> String {
+        use self::Input::*;
+        match self {
+            &Place(c, i)        => format!("place {} {} {}", c.0, c.1, i),
+            &Law(i)             => format!("law {}", i),
+            &Repeal(i)          => format!("repeal {}", i),
+            &Prioritise(c)      => format!("priority {} {}", c.0, c.1),
+            &Road(c)            => format!("road {} {}", c.0, c.1),
+            &Demolish(c)        => format!("demolish {} {}", c.0, c.1),
+            &Undo               => "undo".to_string(),
+            &Redo               => "redo".to_string(),
+            &EndTurn            => "end".to_string(),
+        }
+    }
+
+    /// The tile, the input refers to.
+    pub fn coord(&self) -> Option<Coord> {
+        use self::Input::*;
+        match self {
+            &Place(c, _) | &Prioritise(c) | &Road(c) | &Demolish(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn parse(words: &[&str]) -> Result<Input, String> {
+        use self::Input::*;
+        let n = |i: usize| -> Result<u32, String> {
+            words.get(i)
+                .ok_or_else(|| "missing argument".to_string())
+                .and_then(|w| w.parse().map_err(|_| format!("invalid number `{}`", w)))
+        };
+        let (input, args) = match words.first() {
+            Some(&"place")      => (Place((n(1)?, n(2)?), n(3)?), 3),
+            Some(&"law")        => (Law(n(1)?), 1),
+            Some(&"repeal")     => (Repeal(n(1)?), 1),
+            Some(&"priority")   => (Prioritise((n(1)?, n(2)?)), 2),
+            Some(&"road")       => (Road((n(1)?, n(2)?)), 2),
+            Some(&"demolish")   => (Demolish((n(1)?, n(2)?)), 2),
+            Some(&"undo")       => (Undo, 0),
+            Some(&"redo")       => (Redo, 0),
+            Some(&"end")        => (EndTurn, 0),
+            Some(w)             => return Err(format!("unknown input `{}`", w)),
+            None                => return Err("empty input".to_string()),
+        };
+        if words.len()!=args+1 {
+            return Err(format!("`{}` expects {} arguments", words[0], args));
+        }
+        Ok(input)
+    }
+}
+
+/// The state of the game, that is changed by the inputs.
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub map: Map,
+    pub deck: Deck,
+    pub sim: Simulation,
+    pub rng: GameRng,
+    pub history: History,
+}
+
+impl Session {
+    /// Start a new game on the map.
+    pub fn new(map: Map, seed: u64) -> Session {
+        let mut rng = GameRng::new(seed);
+        let deck = Deck::new(&mut rng);
+        Session {
+            map: map,
+            deck: deck,
+            sim: Simulation::new(),
+            rng: rng,
+            history: History::new(),
+        }
+    }
+
+    /// Continue a saved game.
+    pub fn from_save(game: SaveGame) -> Session {
+        Session {
+            map: game.map,
+            deck: game.deck,
+            sim: game.sim,
+            rng: game.rng,
+            history: History::new(),
+        }
+    }
+
+    /// Save game of the current state with the given view.
+    pub fn to_save(&self, state: State, zoom: f64, shift: [f64;2]) -> SaveGame {
+        SaveGame {
+            map: self.map.clone(),
+            deck: self.deck.clone(),
+            state: state,
+            sim: self.sim.clone(),
+            rng: self.rng.clone(),
+            zoom: zoom,
+            shift: shift,
+        }
+    }
+
+    /// Carry out an input. Inputs, that aren't possible, e.g. after the game
+    /// has ended, are ignored. Returns the report, if the turn ended.
+    pub fn perform(&mut self, input: &Input) -> Option<TurnReport> {
+        use self::Input::*;
+        if self.sim.game_over || self.sim.victory {
+            return None;
+        }
+        if input.coord().map_or(false, |c| !self.map.contains(c)) {
+            return None;
+        }
+        let cmd = match input {
+            &Place(coord, index) => self.deck.hand().get(index as usize).map(|c| {
+                Command::PlaceCard { coord: coord, card: c.clone(), index: index }
+            }),
+            &Law(index) => self.deck.hand().get(index as usize).map(|c| {
+                Command::EnactLaw { card: c.clone(), index: index }
+            }),
+            &Repeal(index)      => Command::repeal_law(&self.map, index),
+            &Prioritise(coord)  => Some(Command::prioritise(&self.map, coord)),
+            &Road(coord)        => Some(Command::BuildRoad(coord)),
+            &Demolish(coord)    => Command::demolish(&self.map, coord),
+            &Undo => {
+                self.history.undo(&mut self.map, &mut self.deck);
+                None
+            }
+            &Redo => {
+                self.history.redo(&mut self.map, &mut self.deck);
+                None
+            }
+            &EndTurn => {
+                self.history.clear();
+                let report = self.sim.end_turn(&mut self.map, &mut self.rng);
+                self.deck.end_turn(&mut self.rng);
+                return Some(report);
+            }
+        };
+        if let Some(cmd) = cmd {
+            self.history.execute(cmd, &mut self.map, &mut self.deck);
+        }
+        None
+    }
+}
+
+/// Writes the inputs to a replay file as they happen.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    /// Start a new replay file for a game starting at `start`.
+    pub fn create<P: AsRef<Path>>(path: P, start: &SaveGame) -> io::Result<Recorder> {
+        let mut file = File::create(path)?;
+        let save = start.to_save_string();
+        write!(file, "ld38 replay {}\nstart {}\n{}", REPLAY_VERSION, save.lines().count(), save)?;
+        file.flush()?;
+        Ok(Recorder { file: file })
+    }
+
+    pub fn record(&mut self, turn: u32, input: &Input) -> io::Result<()> {
+        writeln!(self.file, "{} {}", turn, input.to_line())?;
+        self.file.flush()
+    }
+}
+
+/// A recorded game.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub start: SaveGame,
+    /// The inputs with the turn they were made in.
+    pub inputs: Vec<(u32, Input)>,
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, DefError> {
+        let path = path.as_ref();
+        let s = read_file(path)?;
+        Replay::parse(&path.display().to_string(), &s)
+    }
+
+    /// Parse a replay. `file` is only used for error messages.
+    pub fn parse(file: &str, data: &str) -> Result<Replay, DefError> {
+        let lines: Vec<&str> = data.lines().collect();
+        let error = |line: usize, msg: String| DefError {
+            file: file.to_string(),
+            line: line as u64+1,
+            column: 0,
+            msg: msg,
+        };
+
+        if lines.get(0).map(|l| l.trim())!=Some(&*format!("ld38 replay {}", REPLAY_VERSION)) {
+            return Err(error(0, "not a replay".to_string()));
+        }
+        let len = lines.get(1)
+            .and_then(|l| {
+                let words: Vec<&str> = l.split_whitespace().collect();
+                if words.len()==2 && words[0]=="start" {
+                    words[1].parse::<usize>().ok()
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| error(1, "expected `start <lines>`".to_string()))?;
+        if lines.len()<2+len {
+            return Err(error(lines.len(), "unexpected end of file".to_string()));
+        }
+
+        let start = SaveGame::parse(file, &lines[2..2+len].join("\n"))
+            .map_err(|mut e| {
+                e.line += 2;
+                e
+            })?;
+
+        let mut inputs = Vec::new();
+        for (i, l) in lines.iter().enumerate().skip(2+len) {
+            let words: Vec<&str> = l.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let turn = words[0].parse::<u32>()
+                .map_err(|_| error(i, format!("invalid turn `{}`", words[0])))?;
+            let input = Input::parse(&words[1..]).map_err(|e| error(i, e))?;
+            match input.coord() {
+                Some(c) if !start.map.contains(c) =>
+                    return Err(error(i, format!("{:?} is outside of the map", c))),
+                _ => {}
+            }
+            inputs.push((turn, input));
+        }
+
+        Ok(Replay {
+            start: start,
+            inputs: inputs,
+        })
+    }
+
+    /// Play the replay and return the final state with the reports of all
+    /// turns. Fails, if the game doesn't run the same way as recorded.
+    pub fn run(&self) -> Result<(Session, Vec<TurnReport>), String> {
+        let mut session = Session::from_save(self.start.clone());
+        let mut reports = Vec::new();
+        for &(turn, ref input) in self.inputs.iter() {
+            if session.sim.turn!=turn {
+                return Err(format!("`{}` recorded in turn {}, but played in turn {}",
+                                   input.to_line(), turn, session.sim.turn));
+            }
+            reports.extend(session.perform(input));
+        }
+        Ok((session, reports))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session::new(test_map(), 2)
+    }
+
+    fn replay(start: &Session, inputs: &str) -> String {
+        let save = start.to_save(State::Def, 1.0, [0.0, 0.0]).to_save_string();
+        format!("ld38 replay 1\nstart {}\n{}{}", save.lines().count(), save, inputs)
+    }
+
+    #[test]
+    fn inputs() {
+        let all = vec![Input::Place((1,2), 3), Input::Law(1), Input::Repeal(0),
+                       Input::Prioritise((0,1)), Input::Road((2,2)), Input::Demolish((1,1)),
+                       Input::Undo, Input::Redo, Input::EndTurn];
+        for input in all {
+            let line = input.to_line();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(Input::parse(&words), Ok(input));
+        }
+        assert!(Input::parse(&["place", "1"]).is_err());
+        assert!(Input::parse(&["end", "1"]).is_err());
+        assert!(Input::parse(&["build"]).is_err());
+    }
+
+    #[test]
+    fn playback() {
+        let start = session();
+        let mut live = start.clone();
+        let farm = live.deck.hand().iter()
+            .position(|c| c==&Card::named("farm").unwrap())
+            .unwrap() as u32;
+        let inputs = vec![Input::Place((0,1), farm), Input::EndTurn, Input::EndTurn];
+
+        let mut log = String::new();
+        for input in inputs.iter() {
+            log += &format!("{} {}\n", live.sim.turn, input.to_line());
+            live.perform(input);
+        }
+
+        let replay = Replay::parse("test", &replay(&start, &log)).unwrap();
+        assert_eq!(replay.inputs.len(), 3);
+        let (end, reports) = replay.run().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(end.sim.turn, 3);
+        assert_eq!(end.map.to_map_string(), live.map.to_map_string());
+        assert_eq!(end.deck, live.deck);
+        assert_eq!(end.rng, live.rng);
+    }
+
+    #[test]
+    fn errors() {
+        let start = session();
+        let err = |inputs: &str| Replay::parse("test", &replay(&start, inputs)).unwrap_err().line;
+        let lines = 2 + start.to_save(State::Def, 1.0, [0.0, 0.0])
+            .to_save_string().lines().count() as u64;
+        assert_eq!(err("1 end\nx end"), lines+2);
+        assert_eq!(err("1 jump"), lines+1);
+        assert_eq!(err("1 end\n2 road 9 9"), lines+2);
+        assert_eq!(err("1 place 2 0 0"), lines+1);
+        assert!(Replay::parse("test", "ld38 replay 1\nstart 100").is_err());
+
+        let replay = Replay::parse("test", &replay(&start, "2 end")).unwrap();
+        assert!(replay.run().is_err());
+    }
+
+    #[test]
+    fn outside_of_the_map() {
+        let mut s = session();
+        s.map.stock.wood = 100;
+        let before = s.map.to_map_string();
+        s.perform(&Input::Road((9,9)));
+        s.perform(&Input::Place((2,0), 0));
+        assert_eq!(s.map.to_map_string(), before);
+    }
+}
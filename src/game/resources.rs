@@ -0,0 +1,92 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul};
+
+/// A bundle of goods, used both for stockpiles and for production.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Resources {
+    pub food: u32,
+    pub wood: u32,
+    pub coal: u32,
+    pub iron: u32,
+    pub stone: u32,
+}
+
+impl Resources {
+    pub fn new() -> Resources {
+        Resources::default()
+    }
+
+    /// Checks whether `other` can be paid from `self`.
+    pub fn contains(&self, other: &Resources) -> bool {
+        self.food>=other.food && self.wood>=other.wood && self.coal>=other.coal
+            && self.iron>=other.iron && self.stone>=other.stone
+    }
+
+    /// Subtract as much as possible of `other`.
+    pub fn saturating_sub(&self, other: &Resources) -> Resources {
+        Resources {
+            food: self.food.saturating_sub(other.food),
+            wood: self.wood.saturating_sub(other.wood),
+            coal: self.coal.saturating_sub(other.coal),
+            iron: self.iron.saturating_sub(other.iron),
+            stone: self.stone.saturating_sub(other.stone),
+        }
+    }
+}
+
+impl Add for Resources {
+    type Output = Resources;
+    fn add(self, o: Resources) -> Resources {
+        Resources {
+            food: self.food + o.food,
+            wood: self.wood + o.wood,
+            coal: self.coal + o.coal,
+            iron: self.iron + o.iron,
+            stone: self.stone + o.stone,
+        }
+    }
+}
+
+impl AddAssign for Resources {
+    fn add_assign(&mut self, o: Resources) {
+        *self = *self + o;
+    }
+}
+
+/// Scale, e.g. by the staffing of a card. Rounds down.
+impl Mul<f64> for Resources {
+    type Output = Resources;
+    fn mul(self, f: f64) -> Resources {
+        let s = |v: u32| (v as f64*f) as u32;
+        Resources {
+            food: s(self.food),
+            wood: s(self.wood),
+            coal: s(self.coal),
+            iron: s(self.iron),
+            stone: s(self.stone),
+        }
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Food {}  Wood {}  Coal {}  Iron {}  Stone {}",
+               self.food, self.wood, self.coal, self.iron, self.stone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let a = Resources { food: 10, wood: 5, ..Resources::new() };
+        let b = Resources { food: 20, iron: 1, ..Resources::new() };
+        assert_eq!(a+b, Resources { food: 30, wood: 5, iron: 1, ..Resources::new() });
+        assert_eq!(b*0.5, Resources { food: 10, ..Resources::new() });
+        assert_eq!(a.saturating_sub(&b), Resources { wood: 5, ..Resources::new() });
+        assert!((a+b).contains(&a));
+        assert!(!a.contains(&b));
+    }
+}
@@ -0,0 +1,60 @@
+use rand::Rng;
+
+/// The random number generator of a game. Everything random, that affects the
+/// game, must be drawn from it, so a game can be replayed from its seed.
+///
+/// Xorshift128, the state can be stored in save games.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRng {
+    pub seed: u64,
+    pub state: [u32;4],
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        // Xorshift must not be seeded with zeros.
+        GameRng {
+            seed: seed,
+            state: [seed as u32 ^ 0x193a_6754, (seed>>32) as u32 ^ 0xa8a7_d469,
+                    0x9783_0e05, 0x113b_a7bb],
+        }
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let s = &mut self.state;
+        let t = s[0] ^ (s[0] << 11);
+        s[0] = s[1];
+        s[1] = s[2];
+        s[2] = s[3];
+        s[3] = s[3] ^ (s[3] >> 19) ^ (t ^ (t >> 8));
+        s[3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, XorShiftRng};
+
+    #[test]
+    fn reproducible() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        let xs: Vec<u32> = (0..10).map(|_| a.gen()).collect();
+        let ys: Vec<u32> = (0..10).map(|_| b.gen()).collect();
+        assert_eq!(xs, ys);
+        let mut c = GameRng::new(43);
+        assert!(xs != (0..10).map(|_| c.gen()).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn same_as_xorshift() {
+        let mut a = GameRng::new(7);
+        let mut b = XorShiftRng::from_seed(a.state);
+        for _ in 0..10 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+}
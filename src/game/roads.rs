@@ -0,0 +1,179 @@
+//! Roads and the transport of the goods from the cards to the cities.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use super::*;
+
+/// Resources needed to build a road on a tile.
+pub const ROAD_COST: Resources = Resources { food: 0, wood: 10, coal: 0, iron: 0, stone: 0 };
+/// Cost of moving onto a tile with a road or city.
+const ROAD_STEP: u32 = 1;
+/// Cost of moving onto any other tile.
+const OFFROAD_STEP: u32 = 2;
+/// Transport costs up to this value don't reduce the delivered goods.
+const FREE_TRANSPORT: u32 = 4;
+/// Fraction of the goods lost per point of transport cost above `FREE_TRANSPORT`.
+const TRANSPORT_LOSS: f64 = 0.1;
+
+impl Map {
+    /// Is there a road or city on the tile?
+    pub fn has_road(&self, coord: Coord) -> bool {
+        if !self.contains(coord) {
+            return false;
+        }
+        self.roads.contains(&coord) || match self.tile(coord) {
+            &Tile::City(_)  => true,
+            _               => false,
+        }
+    }
+
+    pub fn can_build_road(&self, coord: Coord) -> bool {
+        self.contains(coord) && !self.has_road(coord) && self.stock.contains(&ROAD_COST)
+    }
+
+    pub fn build_road(&mut self, coord: Coord) {
+        assert!(self.can_build_road(coord));
+        self.stock = self.stock.saturating_sub(&ROAD_COST);
+        self.roads.insert(coord);
+    }
+
+    /// Cost of the cheapest path from every tile to the nearest city, indexed
+    /// like `tiles`. `None` if there are no cities.
+    pub fn transport_costs(&self) -> Vec<Option<u32>> {
+        let index = |c: Coord| (c.1*self.width + c.0) as usize;
+        let mut costs = vec![None; self.tiles.len()];
+        let mut queue = BinaryHeap::new();
+        for (c, _) in self.cities() {
+            costs[index(c)] = Some(0);
+            queue.push(Reverse((0, c)));
+        }
+
+        // Dijkstra backwards from all cities at once. A step costs depending on
+        // the tile, that is entered on the way to the city.
+        while let Some(Reverse((cost, c))) = queue.pop() {
+            if costs[index(c)].map_or(false, |e| e<cost) {
+                continue;
+            }
+            let step = if self.has_road(c) { ROAD_STEP } else { OFFROAD_STEP };
+            for n in self.neighbours(c) {
+                let new = cost + step;
+                if costs[index(n)].map_or(true, |e| new<e) {
+                    costs[index(n)] = Some(new);
+                    queue.push(Reverse((new, n)));
+                }
+            }
+        }
+        costs
+    }
+
+    /// Fraction of the goods produced on the tile, that arrives in a city.
+    pub fn delivery(&self, coord: Coord) -> f64 {
+        delivered(self.transport_costs()[(coord.1*self.width + coord.0) as usize])
+    }
+
+    /// Roads connected to a city.
+    pub fn road_network(&self) -> HashSet<Coord> {
+        let mut network = HashSet::new();
+        let mut todo: Vec<Coord> = self.cities().into_iter().map(|(c, _)| c).collect();
+        while let Some(c) = todo.pop() {
+            if !network.insert(c) {
+                continue;
+            }
+            todo.extend(self.neighbours(c).into_iter().filter(|&n| self.has_road(n)));
+        }
+        network
+    }
+
+    /// Is the tile on or next to a road connected to a city?
+    pub fn connected(&self, coord: Coord) -> bool {
+        self.connected_with(coord, &self.road_network())
+    }
+
+    /// Like `connected` with the `road_network`, when many tiles are checked.
+    pub fn connected_with(&self, coord: Coord, network: &HashSet<Coord>) -> bool {
+        network.contains(&coord) || self.neighbours(coord).iter().any(|c| network.contains(c))
+    }
+
+    /// Overlay showing a road on a tile of the given size.
+    pub fn draw_road(&self, coord: Coord, size: f64) -> Graphics {
+        let w = size/5.0;
+        let mid = (size-w)/2.0;
+        let color = [0.5, 0.4, 0.3, 1.0];
+        let mut gr = vec![Rectangle(w, w).color(color).translate([mid, mid])];
+        for n in self.neighbours(coord).into_iter().filter(|&n| self.has_road(n)) {
+            let arm = if n.0<coord.0 {
+                Rectangle(mid, w).translate([0.0, mid])
+            } else if n.0>coord.0 {
+                Rectangle(mid, w).translate([mid+w, mid])
+            } else if n.1<coord.1 {
+                Rectangle(w, mid).translate([mid, 0.0])
+            } else {
+                Rectangle(w, mid).translate([mid, mid+w])
+            };
+            gr.push(arm.color(color));
+        }
+        Group(gr)
+    }
+}
+
+/// Fraction of the goods, that arrive with the given transport cost.
+pub fn delivered(cost: Option<u32>) -> f64 {
+    match cost {
+        Some(cost) => {
+            let extra = cost.saturating_sub(FREE_TRANSPORT);
+            (1.0 - extra as f64*TRANSPORT_LOSS).max(0.0)
+        }
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::Tile::*;
+
+    fn map() -> Map {
+        Map::new(7,1, vec![City(1000), Farmland, Farmland, Farmland,
+                           Farmland, Farmland, Farmland])
+    }
+
+    #[test]
+    fn transport() {
+        let mut map = map();
+        assert_eq!(map.transport_costs(),
+                   vec![Some(0), Some(1), Some(3), Some(5), Some(7), Some(9), Some(11)]);
+        assert_eq!(map.delivery((2,0)), 1.0);
+        assert_eq!(map.delivery((4,0)), 0.7);
+
+        map.stock.wood = 2*ROAD_COST.wood;
+        map.build_road((1,0));
+        map.build_road((2,0));
+        assert_eq!(map.transport_costs()[4], Some(5));
+        assert!(!map.can_build_road((3,0)));
+        map.stock.wood = ROAD_COST.wood;
+        assert!(!map.can_build_road((7,0)));
+        assert!(!map.has_road((0,1)));
+    }
+
+    #[test]
+    fn network() {
+        let mut map = map();
+        map.roads.insert((1,0));
+        map.roads.insert((2,0));
+        map.roads.insert((5,0));
+        assert!(map.connected((3,0)));
+        assert!(!map.connected((4,0)));
+        assert!(!map.connected((6,0)));
+
+        // Roads extend the range of the cards.
+        let farm = Card::named("farm").unwrap();
+        assert!(map.matches((3,0), &farm));
+        assert!(!map.matches((4,0), &farm));
+    }
+
+    #[test]
+    fn no_cities() {
+        let map = Map::new(2,1, vec![Farmland, Farmland]);
+        assert_eq!(map.delivery((0,0)), 0.0);
+    }
+}
@@ -0,0 +1,272 @@
+//! Save games.
+//!
+//! A save file starts with a version line, followed by the state of the
+//! session and finally the map in the format of `map_file`:
+//!
+//! ```text
+//! ld38 save 4
+//! turn 3 0 0
+//! rng 42 1 2 3 4
+//! view 1.6 0 0
+//! stock 400 50 0 0 0
+//! draw farm lumber farm
+//! hand farm lumber farm farm lumber
+//! discard
+//! state place farm 2
+//! map
+//! size 2 3
+//! ...
+//! ```
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use super::*;
+
+pub const SAVE_VERSION: u32 = 4;
+
+/// Everything needed to continue a session.
+#[derive(Clone, Debug)]
+pub struct SaveGame {
+    pub map: Map,
+    pub deck: Deck,
+    pub state: State,
+    pub sim: Simulation,
+    pub rng: GameRng,
+    pub zoom: f64,
+    pub shift: [f64;2],
+}
+
+pub fn save_game<P: AsRef<Path>>(path: P, game: &SaveGame) -> io::Result<()> {
+    let mut f = File::create(path)?;
+    f.write_all(game.to_save_string().as_bytes())
+}
+
+pub fn load_game<P: AsRef<Path>>(path: P) -> Result<SaveGame, DefError> {
+    let path = path.as_ref();
+    let s = read_file(path)?;
+    SaveGame::parse(&path.display().to_string(), &s)
+}
+
+impl SaveGame {
+    pub fn to_save_string(&self) -> String {
+        let s = &self.map.stock;
+        let names = |cards: &[Card]| cards.iter()
+            .map(|c| c.def().name.clone())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let state = match self.state {
+            State::Def                  => "def".to_string(),
+            State::PlaceCard(ref c, i)  => format!("place {} {}", c.def().name, i),
+            State::BuildRoad            => "road".to_string(),
+            State::Demolish             => "demolish".to_string(),
+        };
+
+        let r = &self.rng.state;
+
+        format!("ld38 save {}\nturn {} {} {}\nrng {} {} {} {} {}\nview {} {} {}\n\
+                 stock {} {} {} {} {}\ndraw {}\nhand {}\ndiscard {}\nstate {}\nmap\n{}",
+                SAVE_VERSION,
+                self.sim.turn, self.sim.game_over as u32, self.sim.victory as u32,
+                self.rng.seed, r[0], r[1], r[2], r[3],
+                self.zoom, self.shift[0], self.shift[1],
+                s.food, s.wood, s.coal, s.iron, s.stone,
+                names(self.deck.draw_pile()),
+                names(self.deck.hand()),
+                names(self.deck.discard_pile()),
+                state,
+                self.map.to_map_string())
+    }
+
+    /// Parse a save game. `file` is only used for error messages.
+    pub fn parse(file: &str, data: &str) -> Result<SaveGame, DefError> {
+        let mut lines = data.lines().enumerate();
+        let error = |line: usize, msg: String| DefError {
+            file: file.to_string(),
+            line: line as u64+1,
+            column: 0,
+            msg: msg,
+        };
+
+        let mut next = |key: &str| -> Result<(usize, Vec<&str>), DefError> {
+            match lines.next() {
+                Some((i, l)) => {
+                    let mut words: Vec<&str> = l.split_whitespace().collect();
+                    if words.first()!=Some(&key) {
+                        return Err(error(i, format!("expected `{}`", key)));
+                    }
+                    words.remove(0);
+                    Ok((i, words))
+                }
+                None => Err(error(data.lines().count(), "unexpected end of file".to_string())),
+            }
+        };
+
+        fn numbers<T, E>(line: (usize, Vec<&str>), n: usize, error: &E)
+            -> Result<Vec<T>, DefError>
+        where T: FromStr, E: Fn(usize, String) -> DefError
+        {
+            let (i, words) = line;
+            if words.len()!=n {
+                return Err(error(i, format!("expected {} values", n)));
+            }
+            words.iter()
+                .map(|w| w.parse().map_err(|_| error(i, format!("invalid number `{}`", w))))
+                .collect()
+        }
+
+        let (i, version) = next("ld38")?;
+        match (version.get(0), version.get(1).and_then(|v| v.parse::<u32>().ok())) {
+            (Some(&"save"), Some(SAVE_VERSION)) => {}
+            (Some(&"save"), Some(v)) =>
+                return Err(error(i, format!("unsupported save version {}", v))),
+            _ => return Err(error(i, "not a save game".to_string())),
+        }
+
+        let turn: Vec<u32> = numbers(next("turn")?, 3, &error)?;
+        let (i, words) = next("rng")?;
+        if words.len()!=5 {
+            return Err(error(i, "expected 5 values".to_string()));
+        }
+        let seed: Vec<u64> = numbers((i, words[..1].to_vec()), 1, &error)?;
+        let rng_state: Vec<u32> = numbers((i, words[1..].to_vec()), 4, &error)?;
+        let view: Vec<f64> = numbers(next("view")?, 3, &error)?;
+        let stock: Vec<u32> = numbers(next("stock")?, 5, &error)?;
+
+        let card = |i: usize, name: &str| Card::named(name)
+            .ok_or_else(|| error(i, format!("unknown card `{}`", name)));
+
+        let (draw_pile, hand, discard) = {
+            let mut pile = |key: &str| -> Result<Vec<Card>, DefError> {
+                let (i, names) = next(key)?;
+                names.iter().map(|name| card(i, name)).collect()
+            };
+            (pile("draw")?, pile("hand")?, pile("discard")?)
+        };
+
+        let (i, words) = next("state")?;
+        let state = match (words.get(0), words.len()) {
+            (Some(&"def"), 1)       => State::Def,
+            (Some(&"road"), 1)      => State::BuildRoad,
+            (Some(&"demolish"), 1)  => State::Demolish,
+            (Some(&"place"), 3) => {
+                let index = words[2].parse::<u32>()
+                    .map_err(|_| error(i, format!("invalid number `{}`", words[2])))?;
+                State::PlaceCard(card(i, words[1])?, index)
+            }
+            _ => return Err(error(i, "invalid state".to_string())),
+        };
+
+        let (i, _) = next("map")?;
+        let rest: Vec<&str> = data.lines().skip(i+1).collect();
+        let mut map = Map::parse(file, &rest.join("\n"))
+            .map_err(|mut e| {
+                e.line += i as u64+1;
+                e
+            })?;
+        map.stock = Resources {
+            food: stock[0],
+            wood: stock[1],
+            coal: stock[2],
+            iron: stock[3],
+            stone: stock[4],
+        };
+
+        Ok(SaveGame {
+            map: map,
+            deck: Deck::from_piles(draw_pile, hand, discard),
+            state: state,
+            sim: Simulation {
+                turn: turn[0],
+                game_over: turn[1]!=0,
+                victory: turn[2]!=0,
+            },
+            rng: GameRng {
+                seed: seed[0],
+                state: [rng_state[0], rng_state[1], rng_state[2], rng_state[3]],
+            },
+            zoom: view[0],
+            shift: [view[1], view[2]],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn game() -> SaveGame {
+        let farm = Card::named("farm").unwrap();
+        let lumber = Card::named("lumber").unwrap();
+        let mut map = test_map();
+        map.place_card((0,1), farm.clone());
+        map.stock.food = 400;
+        map.stock.iron = 3;
+        SaveGame {
+            map: map,
+            deck: Deck::from_piles(vec![lumber.clone()],
+                                   vec![farm.clone(), lumber, farm.clone()],
+                                   vec![]),
+            state: State::PlaceCard(farm, 2),
+            sim: Simulation { turn: 7, game_over: false, victory: true },
+            rng: GameRng::new(12345),
+            zoom: 1.6,
+            shift: [-12.5, 30.25],
+        }
+    }
+
+    fn assert_same(a: &SaveGame, b: &SaveGame) {
+        assert_eq!(a.map.tiles, b.map.tiles);
+        assert_eq!(a.map.cards, b.map.cards);
+        assert_eq!(a.map.stock, b.map.stock);
+        assert_eq!(a.deck, b.deck);
+        assert_eq!(a.state, b.state);
+        assert_eq!(a.sim.turn, b.sim.turn);
+        assert_eq!(a.sim.game_over, b.sim.game_over);
+        assert_eq!(a.sim.victory, b.sim.victory);
+        assert_eq!(a.rng, b.rng);
+        assert_eq!(a.zoom, b.zoom);
+        assert_eq!(a.shift, b.shift);
+    }
+
+    #[test]
+    fn round_trip() {
+        let g = game();
+        let s = g.to_save_string();
+        assert_same(&g, &SaveGame::parse("test", &s).unwrap());
+
+        let g = SaveGame { state: State::Def, ..game() };
+        let s = g.to_save_string();
+        assert_same(&g, &SaveGame::parse("test", &s).unwrap());
+
+        let g = SaveGame { state: State::BuildRoad, ..game() };
+        let s = g.to_save_string();
+        assert_same(&g, &SaveGame::parse("test", &s).unwrap());
+    }
+
+    #[test]
+    fn round_trip_file() {
+        let path = env::temp_dir().join("ld38_round_trip.sav");
+        let g = game();
+        save_game(&path, &g).unwrap();
+        assert_same(&g, &load_game(&path).unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let s = game().to_save_string();
+        let e = SaveGame::parse("test", &s.replace("save 4", "save 99")).unwrap_err();
+        assert_eq!(e.line, 1);
+        let e = SaveGame::parse("test", &s.replace("lumber farm", "lumber castle")).unwrap_err();
+        assert_eq!(e.line, 7);
+        let e = SaveGame::parse("test", &s.replace("city 1 1", "city 0 1")).unwrap_err();
+        assert_eq!(e.line, 15);
+        let e = SaveGame::parse("test", "ld38 save 4\nturn 1 0 0").unwrap_err();
+        assert_eq!(e.line, 3);
+        let e = SaveGame::parse("test", "ld38 save 4\nturn 1 0 0\nrng 1 4294967296 0 0 0")
+            .unwrap_err();
+        assert_eq!((e.line, e.msg.as_str()), (3, "invalid number `4294967296`"));
+    }
+}
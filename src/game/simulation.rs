@@ -0,0 +1,141 @@
+use super::*;
+
+/// Population growth per turn, if there is enough food.
+const GROWTH: f64 = 0.05;
+/// Food gathered by the surroundings of a city without any farms.
+const CITY_FOOD: u32 = 1000;
+
+/// Summary of a single turn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurnReport {
+    pub turn: u32,
+    pub pops: u32,
+    pub nec_pops: u32,
+    pub production: Resources,
+    pub events: Vec<Event>,
+    pub game_over: bool,
+    pub victory: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Simulation {
+    pub turn: u32,
+    /// All people are gone.
+    pub game_over: bool,
+    /// All goals of the map are reached.
+    pub victory: bool,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        Simulation {
+            turn: 1,
+            game_over: false,
+            victory: false,
+        }
+    }
+
+    /// Run the economy for one turn.
+    pub fn end_turn(&mut self, map: &mut Map, rng: &mut GameRng) -> TurnReport {
+        let nec_pops = map.nec_pops();
+
+        let production = produce(map);
+        map.stock += production;
+        let growth = GROWTH + map.law_effects().growth as f64/100.0;
+        map.feed_cities(growth);
+        map.build_housing();
+
+        let events = roll_events(map, rng);
+        for e in events.iter() {
+            e.apply(map);
+        }
+
+        let pops = map.pops();
+        if pops==0 {
+            self.game_over = true;
+        } else if map.goals_reached(self.turn) {
+            self.victory = true;
+        }
+
+        let report = TurnReport {
+            turn: self.turn,
+            pops: pops,
+            nec_pops: nec_pops,
+            production: production,
+            events: events,
+            game_over: self.game_over,
+            victory: self.victory,
+        };
+        self.turn += 1;
+        report
+    }
+}
+
+fn produce(map: &Map) -> Resources {
+    use self::Tile::*;
+
+    let cities: u32 = map.tiles.iter()
+        .map(|e| match e {
+            &City(_)    => CITY_FOOD,
+            _           => 0,
+        })
+        .sum();
+
+    map.production() + Resources { food: cities, ..Resources::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::*;
+    use self::Tile::*;
+
+    #[test]
+    fn growth() {
+        let mut map = test_map();
+        map.place_card((0,1),Card::named("farm").unwrap());
+        let mut sim = Simulation::new();
+        // No events happen with this seed.
+        let report = sim.end_turn(&mut map, &mut GameRng::new(0xdead_beef));
+        assert!(report.events.is_empty());
+        assert_eq!(report.turn, 1);
+        assert_eq!(report.production.food, 1400);
+        assert_eq!(report.production.wood, 62);
+        assert_eq!(map.pops(), 1050);
+        assert_eq!(map.stock.food, 350);
+        // The wood is used for houses in the crowded city.
+        assert_eq!(map.stock.wood, 62-2*HOUSE_WOOD);
+        assert_eq!(map.housing((1,1)), BASE_HOUSING+2*HOUSE_SIZE);
+        assert!(!report.game_over);
+        assert_eq!(sim.turn, 2);
+    }
+
+    #[test]
+    fn starvation() {
+        let mut map = Map::new(2,1, vec![City(2000), Forrest]);
+        let report = Simulation::new().end_turn(&mut map, &mut GameRng::new(0xdead_beef));
+        assert!(report.events.is_empty());
+        assert_eq!(map.pops(), 1000);
+        assert_eq!(map.stock.food, 0);
+    }
+
+    #[test]
+    fn game_over() {
+        let mut map = Map::new(1,1, vec![Farmland]);
+        let mut sim = Simulation::new();
+        assert!(sim.end_turn(&mut map, &mut GameRng::new(0)).game_over);
+        assert!(sim.game_over);
+        assert!(!sim.victory);
+    }
+
+    #[test]
+    fn victory() {
+        let mut map = test_map();
+        map.goals = vec![Goal::Survive(2)];
+        let mut sim = Simulation::new();
+        let mut rng = GameRng::new(2);
+        assert!(!sim.end_turn(&mut map, &mut rng).victory);
+        assert!(sim.end_turn(&mut map, &mut rng).victory);
+        assert!(sim.victory);
+    }
+}
@@ -0,0 +1,86 @@
+//! Upgrading placed cards by playing the same card on them again, and
+//! demolishing them.
+
+use super::*;
+
+/// Highest level of a card.
+pub const MAX_LEVEL: u32 = 3;
+/// Additional production per level above the first in percent.
+const LEVEL_PRODUCTION: i32 = 75;
+/// Additional workers per level above the first in percent.
+const LEVEL_WORKERS: i32 = 50;
+
+impl Map {
+    /// Level of the card on the tile.
+    pub fn level(&self, coord: Coord) -> u32 {
+        self.levels.get(&coord).cloned().unwrap_or(1)
+    }
+
+    /// Can the card on the tile be upgraded by playing `card`?
+    pub fn can_upgrade(&self, coord: Coord, card: &Card) -> bool {
+        self.cards.get(&coord)==Some(card) && self.level(coord)<MAX_LEVEL
+    }
+
+    /// Production of the card on the tile compared to the first level.
+    pub fn level_production(&self, coord: Coord) -> f64 {
+        let percent = LEVEL_PRODUCTION*(self.level(coord) as i32-1);
+        (100 + percent) as f64/100.0
+    }
+
+    /// Workers needed by the card on the tile without any laws.
+    pub fn level_workers(&self, coord: Coord) -> u32 {
+        let workers = self.cards.get(&coord).map_or(0, |c| c.workers());
+        LawEffects::scale(workers, LEVEL_WORKERS*(self.level(coord) as i32-1))
+    }
+
+    pub fn can_demolish(&self, coord: Coord) -> bool {
+        self.cards.contains_key(&coord)
+    }
+
+    /// Remove the card from the tile.
+    pub fn demolish(&mut self, coord: Coord) -> Option<Card> {
+        self.levels.remove(&coord);
+        self.priority.retain(|c| c!=&coord);
+        self.cards.remove(&coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm() -> Card { Card::named("farm").unwrap() }
+
+    #[test]
+    fn upgrade() {
+        let mut map = test_map();
+        assert!(map.place_card((0,1), farm()));
+        let food = map.production().food;
+        let workers = map.nec_pops();
+        assert!(map.matches((0,1), &farm()));
+
+        assert!(map.place_card((0,1), farm()));
+        assert_eq!(map.level((0,1)), 2);
+        assert_eq!(map.production().food, food*175/100);
+        assert_eq!(map.nec_pops(), workers+50);
+
+        assert!(map.place_card((0,1), farm()));
+        assert!(!map.matches((0,1), &farm()));
+        assert!(!map.place_card((0,1), farm()));
+        assert_eq!(map.level((0,1)), MAX_LEVEL);
+    }
+
+    #[test]
+    fn demolish() {
+        let mut map = test_map();
+        map.place_card((0,1), farm());
+        map.place_card((0,1), farm());
+        map.prioritise((0,1));
+        assert!(map.can_demolish((0,1)));
+        assert_eq!(map.demolish((0,1)), Some(farm()));
+        assert_eq!(map.level((0,1)), 1);
+        assert!(map.priority.is_empty());
+        assert!(!map.can_demolish((0,1)));
+        assert_eq!(map.demolish((0,1)), None);
+    }
+}
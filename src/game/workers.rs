@@ -0,0 +1,128 @@
+//! Assignment of the workers of the cities to the cards.
+
+use super::*;
+
+impl Map {
+    /// Workers needed to fully staff the card on the tile.
+    pub fn card_workers(&self, coord: Coord) -> u32 {
+        LawEffects::scale(self.level_workers(coord), self.law_effects().workers)
+    }
+
+    /// Workers a city can spare after its administration.
+    pub fn city_workers(&self, pops: u32) -> u32 {
+        let admin = LawEffects::scale(pops/10, self.law_effects().admin);
+        pops.saturating_sub(admin)
+    }
+
+    /// The placed cards in the order, in which they get workers: first the
+    /// prioritised ones, then the rest from top left to bottom right.
+    pub fn staffing_order(&self) -> Vec<Coord> {
+        let mut order: Vec<Coord> = self.priority.iter()
+            .filter(|c| self.cards.contains_key(c))
+            .cloned()
+            .collect();
+        let mut rest: Vec<Coord> = self.cards.keys()
+            .filter(|c| !order.contains(c))
+            .cloned()
+            .collect();
+        rest.sort_by_key(|c| (c.1, c.0));
+        order.extend(rest);
+        order
+    }
+
+    /// Workers each card gets from which city. The cards take the workers
+    /// from the nearest cities first.
+    pub fn assign_workers(&self) -> HashMap<Coord, Vec<(Coord, u32)>> {
+        let mut cities: Vec<(Coord, u32)> = self.cities().into_iter()
+            .map(|(c, p)| (c, self.city_workers(p)))
+            .collect();
+
+        let mut assignment = HashMap::new();
+        for coord in self.staffing_order() {
+            let mut missing = self.card_workers(coord);
+            let mut workers = Vec::new();
+            cities.sort_by_key(|&(c, _)| (distance(c, coord), c.1, c.0));
+            for &mut (city, ref mut free) in cities.iter_mut() {
+                let n = missing.min(*free);
+                if n>0 {
+                    *free -= n;
+                    missing -= n;
+                    workers.push((city, n));
+                }
+            }
+            assignment.insert(coord, workers);
+        }
+        assignment
+    }
+
+    /// Fraction of the needed workers, every card gets.
+    pub fn staffing(&self) -> HashMap<Coord, f64> {
+        self.assign_workers().into_iter()
+            .map(|(coord, workers)| {
+                let needed = self.card_workers(coord);
+                let got: u32 = workers.iter().map(|&(_, n)| n).sum();
+                let staffed = if needed==0 { 1.0 } else { got as f64/needed as f64 };
+                (coord, staffed)
+            })
+            .collect()
+    }
+
+    /// Give the card at `coord` workers before all others.
+    pub fn prioritise(&mut self, coord: Coord) {
+        let cards = &self.cards;
+        self.priority.retain(|c| c!=&coord && cards.contains_key(c));
+        self.priority.insert(0, coord);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farm() -> Card { Card::named("farm").unwrap() }
+
+    fn map() -> Map {
+        // Two farms, that need 200 workers, next to a small city.
+        let mut map = Map::new(4,1, vec![Tile::Farmland, Tile::City(150),
+                                         Tile::Farmland, Tile::Farmland]);
+        map.place_card((0,0), farm());
+        map.place_card((3,0), farm());
+        map
+    }
+
+    #[test]
+    fn partial_staffing() {
+        let map = map();
+        assert_eq!(map.city_workers(150), 135);
+        let s = map.staffing();
+        assert_eq!(s[&(0,0)], 1.0);
+        assert_eq!(s[&(3,0)], 0.35);
+        assert_eq!(map.production().food, 400 + 140);
+    }
+
+    #[test]
+    fn priority() {
+        let mut map = map();
+        map.prioritise((3,0));
+        assert_eq!(map.staffing_order(), vec![(3,0), (0,0)]);
+        assert_eq!(map.staffing()[&(0,0)], 0.35);
+
+        map.prioritise((0,0));
+        assert_eq!(map.priority, vec![(0,0), (3,0)]);
+    }
+
+    #[test]
+    fn nearest_city() {
+        let mut map = Map::new(5,1, vec![Tile::City(150), Tile::Farmland, Tile::Farmland,
+                                         Tile::Farmland, Tile::City(1000)]);
+        map.place_card((1,0), farm());
+        map.place_card((3,0), farm());
+        let workers = map.assign_workers();
+        assert_eq!(workers[&(1,0)], vec![((0,0), 100)]);
+        assert_eq!(workers[&(3,0)], vec![((4,0), 100)]);
+
+        // The small city can't staff the farm alone.
+        map.tiles[0] = Tile::City(50);
+        assert_eq!(map.assign_workers()[&(1,0)], vec![((0,0), 45), ((4,0), 55)]);
+    }
+}
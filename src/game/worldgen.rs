@@ -0,0 +1,165 @@
+//! Procedural generation of the small world.
+
+use rand::Rng;
+use super::*;
+
+/// Fraction of the mountain tiles, which contain coal or iron.
+const COAL: f64 = 0.15;
+const IRON: f64 = 0.1;
+/// Tiles per city.
+const CITY_AREA: u32 = 60;
+/// Minimal distance between cities.
+const CITY_DIST: u32 = 3;
+
+/// Generate a new map. The same seed always results in the same map.
+pub fn generate(width: u32, height: u32, seed: u64) -> Map {
+    assert!(width>0 && height>0);
+    let mut rng = GameRng::new(seed);
+    let size = (width*height) as usize;
+    let index = |c: Coord| (c.1*width + c.0) as usize;
+    let clamp_coord = |x: i64, y: i64| {
+        (clamp(0, x, width as i64-1) as u32, clamp(0, y, height as i64-1) as u32)
+    };
+
+    // River: a random walk along the longer side of the map.
+    let mut river = vec![false; size];
+    {
+        let along = width.max(height);
+        let across = width.min(height);
+        let mut pos = rng.gen_range(0, across) as i64;
+        for i in 0..along {
+            let c = if width>=height {
+                clamp_coord(i as i64, pos)
+            } else {
+                clamp_coord(pos, i as i64)
+            };
+            river[index(c)] = true;
+            pos = clamp(0, pos + rng.gen_range(-1, 2), across as i64-1);
+        }
+    }
+
+    // Mountain ranges: random walks in a main direction.
+    let mut mountain = vec![false; size];
+    let ranges = 1 + size/50;
+    for _ in 0..ranges {
+        let (dx, dy) = *rng.choose(&[(1,0), (0,1), (1,1), (1,-1)]).unwrap();
+        let mut x = rng.gen_range(0, width) as i64;
+        let mut y = rng.gen_range(0, height) as i64;
+        let len = rng.gen_range(3, 4 + width.max(height)/2);
+        for _ in 0..len {
+            let c = clamp_coord(x, y);
+            if !river[index(c)] {
+                mountain[index(c)] = true;
+            }
+            if rng.gen_weighted_bool(3) {
+                x += rng.gen_range(-1, 2);
+                y += rng.gen_range(-1, 2);
+            } else {
+                x += dx;
+                y += dy;
+            }
+        }
+    }
+
+    // Forests: clusters around random centres.
+    let mut forest = vec![false; size];
+    let clusters = 1 + size/25;
+    for _ in 0..clusters {
+        let centre = (rng.gen_range(0, width), rng.gen_range(0, height));
+        let radius = rng.gen_range(1, 3);
+        for y in 0..height {
+            for x in 0..width {
+                let d = distance(centre, (x,y));
+                if d<=radius && (d==0 || rng.gen::<f64>()<0.7) {
+                    forest[index((x,y))] = true;
+                }
+            }
+        }
+    }
+
+    let near_river = |c: Coord| {
+        (0..height).any(|y| (0..width).any(|x| river[index((x,y))] && distance(c, (x,y))<=1))
+    };
+
+    let mut tiles = Vec::with_capacity(size);
+    for y in 0..height {
+        for x in 0..width {
+            let i = index((x,y));
+            let tile = if mountain[i] {
+                let r = rng.gen::<f64>();
+                if r<COAL {
+                    Tile::Coal
+                } else if r<COAL+IRON {
+                    Tile::Iron
+                } else {
+                    Tile::Mountain
+                }
+            } else if forest[i] && !near_river((x,y)) {
+                Tile::Forrest
+            } else {
+                Tile::Farmland
+            };
+            tiles.push(tile);
+        }
+    }
+
+    // Cities: on farmland, preferably next to the river, with some space in
+    // between.
+    let mut sites: Vec<Coord> = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if tiles[index((x,y))]==Tile::Farmland {
+                sites.push((x,y));
+            }
+        }
+    }
+    rng.shuffle(&mut sites);
+    sites.sort_by_key(|&c| !river[index(c)]);
+    if sites.is_empty() {
+        sites.push((rng.gen_range(0, width), rng.gen_range(0, height)));
+    }
+
+    let cities = 1 + (size as u32)/CITY_AREA;
+    let mut placed: Vec<Coord> = Vec::new();
+    for c in sites {
+        if placed.len() as u32==cities {
+            break;
+        }
+        if placed.iter().all(|&p| distance(p, c)>=CITY_DIST) {
+            tiles[index(c)] = Tile::City(rng.gen_range(8, 13)*100);
+            placed.push(c);
+        }
+    }
+
+    Map::new(width, height, tiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        for seed in 0..10 {
+            assert_eq!(generate(12, 8, seed).tiles, generate(12, 8, seed).tiles);
+        }
+        assert!(generate(12, 8, 1).tiles != generate(12, 8, 2).tiles);
+    }
+
+    #[test]
+    fn terrain() {
+        for seed in 0..20 {
+            let map = generate(12, 8, seed);
+            let count = |f: &Fn(&Tile) -> bool| map.tiles.iter().filter(|t| f(t)).count();
+            assert!(count(&|t| match t { &Tile::City(p) => p>0, _ => false })>=1);
+            assert!(count(&|t| t==&Tile::Farmland)>0);
+            assert!(count(&|t| t==&Tile::Mountain || t==&Tile::Coal || t==&Tile::Iron)>0);
+        }
+    }
+
+    #[test]
+    fn tiny() {
+        let map = generate(1, 1, 0);
+        assert!(map.pops()>0);
+    }
+}
@@ -10,6 +10,7 @@ use self::gfx_core::factory::Factory;
 use ::piston_window::texture::Filter;
 
 use std::fmt::Display;
+use super::TextMetrics;
 
 struct Glyph<R:Resources> {
     bearing: [f64;2],
@@ -96,3 +97,12 @@ where R: Resources,
     }
 }
 
+
+impl<R,F> TextMetrics for FontCache<R,F>
+where R: Resources,
+      F: Factory<R>,
+{
+    fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+        txt.chars().map(|ch| self.character(size, ch).size[0]).sum()
+    }
+}
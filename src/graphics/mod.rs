@@ -1,3 +1,15 @@
 mod font_cache;
+mod scene;
+mod render;
+mod software;
+mod piston;
+#[cfg(test)]
+mod snapshot;
 
 pub use self::font_cache::*;
+pub use self::scene::*;
+pub use self::render::*;
+pub use self::software::*;
+pub use self::piston::*;
+#[cfg(test)]
+pub use self::snapshot::*;
@@ -0,0 +1,55 @@
+//! Renderer drawing to a piston window.
+
+use ::piston_window::{rectangle, text};
+use ::piston_window::character::CharacterCache;
+use ::piston_window::Graphics as Backend;
+use super::render::*;
+use super::TextMetrics;
+
+pub struct PistonRenderer<'a, G: 'a, C: 'a> {
+    /// Transformation of the window, `Context::transform`.
+    pub transform: Matrix2d,
+    pub g: &'a mut G,
+    pub glyphs: &'a mut C,
+}
+
+impl<'a, G, C> PistonRenderer<'a, G, C>
+where G: Backend<Texture=C::Texture>,
+      C: CharacterCache,
+{
+    pub fn new(transform: Matrix2d, g: &'a mut G, glyphs: &'a mut C) -> Self {
+        PistonRenderer {
+            transform: transform,
+            g: g,
+            glyphs: glyphs,
+        }
+    }
+}
+
+impl<'a, G, C> TextMetrics for PistonRenderer<'a, G, C>
+where G: Backend<Texture=C::Texture>,
+      C: CharacterCache,
+{
+    fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+        txt.chars().map(|ch| self.glyphs.character(size, ch).size[0]).sum()
+    }
+}
+
+impl<'a, G, C> Renderer for PistonRenderer<'a, G, C>
+where G: Backend<Texture=C::Texture>,
+      C: CharacterCache,
+{
+    fn rectangle(&mut self, color: [f32;4], size: [f64;2], trans: Matrix2d) {
+        rectangle(color, [0.0, 0.0, size[0], size[1]],
+                  multiply(self.transform, trans), self.g);
+    }
+
+    /// The glyphs are rendered at the size they have on the screen, so they
+    /// stay sharp when zoomed.
+    fn text(&mut self, color: [f32;4], size: u32, txt: &str, trans: Matrix2d) {
+        let s = (get_scale(trans)*size as f64).ceil();
+        let sf = size as f64 / s;
+        text(color, s as u32, txt, self.glyphs,
+             multiply(self.transform, scale(trans, sf)), self.g);
+    }
+}
@@ -0,0 +1,250 @@
+//! Drawing of the scene tree, independent of the backend.
+
+use game::Action;
+use super::{Align, Arrangements, Graphics, TextMetrics, LINE_HEIGHT, text_lines};
+
+/// Affine transformation, the same layout as in piston.
+pub type Matrix2d = [[f64;3];2];
+
+/// A backend, that can draw the primitives of the scene tree. The metrics are
+/// used to arrange the children of layout nodes.
+pub trait Renderer: TextMetrics {
+    /// Fill the rectangle from the origin to `size`.
+    fn rectangle(&mut self, color: [f32;4], size: [f64;2], trans: Matrix2d);
+    /// Draw a line of text with its baseline starting at the origin.
+    fn text(&mut self, color: [f32;4], size: u32, txt: &str, trans: Matrix2d);
+}
+
+pub fn identity() -> Matrix2d {
+    [[1.0, 0.0, 0.0],
+     [0.0, 1.0, 0.0]]
+}
+
+pub fn multiply(m: Matrix2d, b: Matrix2d) -> Matrix2d {
+    let row = |r: [f64;3]| [
+        r[0]*b[0][0] + r[1]*b[1][0],
+        r[0]*b[0][1] + r[1]*b[1][1],
+        r[0]*b[0][2] + r[1]*b[1][2] + r[2],
+    ];
+    [row(m[0]), row(m[1])]
+}
+
+pub fn translate(m: Matrix2d, v: [f64;2]) -> Matrix2d {
+    multiply(m, [[1.0, 0.0, v[0]], [0.0, 1.0, v[1]]])
+}
+
+pub fn scale(m: Matrix2d, s: f64) -> Matrix2d {
+    multiply(m, [[s, 0.0, 0.0], [0.0, s, 0.0]])
+}
+
+pub fn invert(m: Matrix2d) -> Matrix2d {
+    let det = m[0][0]*m[1][1] - m[0][1]*m[1][0];
+    let a = m[1][1]/det;
+    let b = -m[0][1]/det;
+    let c = -m[1][0]/det;
+    let d = m[0][0]/det;
+    [[a, b, -(a*m[0][2] + b*m[1][2])],
+     [c, d, -(c*m[0][2] + d*m[1][2])]]
+}
+
+pub fn transform_pos(m: Matrix2d, p: [f64;2]) -> [f64;2] {
+    [m[0][0]*p[0] + m[0][1]*p[1] + m[0][2],
+     m[1][0]*p[0] + m[1][1]*p[1] + m[1][2]]
+}
+
+/// Vertical scale of the transformation.
+pub fn get_scale(m: Matrix2d) -> f64 {
+    (m[0][1]*m[0][1] + m[1][1]*m[1][1]).sqrt()
+}
+
+pub fn inside(rect: [f64;4], p: [f64;2]) -> bool {
+    let p = [p[0]-rect[0], p[1]-rect[1]];
+    p[0]>=0.0 && p[1]>=0.0 && p[0]<=rect[2] && p[1]<=rect[3]
+}
+
+#[derive(Clone, Debug)]
+enum Prim<'a> {
+    PrimColor([f32;4]),
+    PrimTransform(Matrix2d),
+    PrimDraw(&'a [Graphics]),
+    PrimDrawS(&'a Graphics),
+    PrimClick(Action),
+}
+
+use self::Prim::*;
+
+/// Draw the scene. Returns the action of the last clickable node, that has a
+/// rectangle under `mouse_pos`.
+pub fn render<R: Renderer>(r: &mut R, graphics: &Graphics, mouse_pos: [f64;2]) -> Option<Action> {
+    let mut stack = vec![PrimDrawS(graphics)];
+    let singleton = |gr| PrimDrawS(gr);
+    let mut trans = identity();
+    let mut color = [0.0, 0.0, 0.0, 1.0];
+    let mut hovered = false;
+    let mut hover_action = None;
+    let mut arrangements = Arrangements::new();
+
+    while let Some(e) = stack.pop() {
+        use super::Graphics::*;
+        match e {
+            PrimColor(c)        => { color = c; }
+            PrimTransform(t)    => { trans = t; }
+            PrimClick(c)    => {
+                if hovered {
+                    hover_action = Some(c);
+                }
+            }
+            PrimDrawS(s0)   => {
+                match s0 {
+                    &Rectangle(w,h) => {
+                        r.rectangle(color, [w, h], trans);
+
+                        let p = transform_pos(invert(trans), mouse_pos);
+                        if inside([0.0,0.0,w,h], p) {
+                            hovered = true;
+                        }
+                    }
+                    &Color(col, ref gr) => {
+                        stack.push(PrimColor(color));
+                        color = col;
+                        stack.push(singleton(gr));
+                    }
+                    &Translate(t, ref gr) => {
+                        stack.push(PrimTransform(trans));
+                        trans = translate(trans, t);
+                        stack.push(singleton(gr));
+                    }
+                    &Scale(t, ref gr) => {
+                        stack.push(PrimTransform(trans));
+                        trans = scale(trans, t);
+                        stack.push(singleton(gr));
+                    }
+                    &Text(size,ref txt) => {
+                        draw_text(r, color, size, txt, Align::Start, None, trans);
+                    }
+                    &TextBlock(size, ref txt, align, width) => {
+                        draw_text(r, color, size, txt, align, Some(width), trans);
+                    }
+                    &Group(ref children) => {
+                        stack.push(PrimDraw(children));
+                    }
+                    &Arrange(_, ref children) => {
+                        // Measuring an arrangement measures the nested ones
+                        // too, so they are only looked up.
+                        let key = s0 as *const Graphics;
+                        if !arrangements.contains_key(&key) {
+                            s0.measure(r, &mut arrangements);
+                        }
+                        let offsets = arrangements.remove(&key).unwrap();
+                        stack.push(PrimTransform(trans));
+                        for (c, o) in children.iter().zip(offsets).rev() {
+                            stack.push(singleton(c));
+                            stack.push(PrimTransform(translate(trans, o)));
+                        }
+                    }
+                    &Click(ref ac, ref gr) => {
+                        stack.push(PrimClick(ac.clone()));
+                        stack.push(singleton(gr));
+                        hovered = false;
+                    }
+                }
+            }
+            PrimDraw(gra) => {
+                if let Some((s0,s1)) = gra.split_first() {
+                    stack.push(PrimDraw(s1));
+                    stack.push(PrimDrawS(s0));
+                }
+            }
+        }
+    }
+    hover_action
+}
+
+/// Draw the text line by line.
+fn draw_text<R: Renderer>(r: &mut R, color: [f32;4], size: u32, txt: &str,
+                          align: Align, width: Option<f64>, trans: Matrix2d) {
+    for (i, line) in text_lines(r, size, txt, width).iter().enumerate() {
+        let x = match width {
+            Some(w) => align.offset(r.text_width(size, line), w),
+            None => 0.0,
+        };
+        let y = i as f64*LINE_HEIGHT*size as f64;
+        r.text(color, size, line, translate(trans, [x, y]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Graphics::*;
+
+    /// Remembers the rectangles in screen coordinates and counts the
+    /// measured lines of text.
+    struct Recorder(Vec<([f32;4], [f64;4])>, usize);
+
+    impl Renderer for Recorder {
+        fn rectangle(&mut self, color: [f32;4], size: [f64;2], trans: Matrix2d) {
+            let p = transform_pos(trans, [0.0, 0.0]);
+            let q = transform_pos(trans, size);
+            self.0.push((color, [p[0], p[1], q[0]-p[0], q[1]-p[1]]));
+        }
+        fn text(&mut self, color: [f32;4], size: u32, txt: &str, trans: Matrix2d) {}
+    }
+
+    impl TextMetrics for Recorder {
+        fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+            self.1 += 1;
+            0.0
+        }
+    }
+
+    #[test]
+    fn matrices() {
+        let m = scale(translate(identity(), [10.0, 20.0]), 2.0);
+        assert_eq!(transform_pos(m, [1.0, 1.0]), [12.0, 22.0]);
+        assert_eq!(transform_pos(invert(m), [12.0, 22.0]), [1.0, 1.0]);
+        assert_eq!(get_scale(m), 2.0);
+    }
+
+    #[test]
+    fn transforms_and_colors() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let gr = Group(vec![
+            Rectangle(1.0, 2.0).translate([5.0, 5.0]).scale(2.0).color(red),
+            Rectangle(3.0, 3.0),
+        ]);
+        let mut r = Recorder(Vec::new(), 0);
+        render(&mut r, &gr, [0.0, 0.0]);
+        assert_eq!(r.0, vec![(red, [10.0, 10.0, 2.0, 4.0]),
+                             ([0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 3.0, 3.0])]);
+    }
+
+    #[test]
+    fn layout() {
+        let gr = Graphics::row(vec![Rectangle(1.0, 1.0), Rectangle(2.0, 2.0)], 3.0)
+            .translate([10.0, 0.0]);
+        let mut r = Recorder(Vec::new(), 0);
+        render(&mut r, &Group(vec![gr, Rectangle(1.0, 1.0)]), [0.0, 0.0]);
+        let rects: Vec<[f64;4]> = r.0.iter().map(|&(_, rect)| rect).collect();
+        assert_eq!(rects, vec![[10.0, 0.0, 1.0, 1.0], [14.0, 0.0, 2.0, 2.0],
+                               [0.0, 0.0, 1.0, 1.0]]);
+
+        // Nested arrangements are measured once.
+        let gr = Text(10, "ab".to_string()).padding(1.0).padding(1.0).padding(1.0);
+        let mut r = Recorder(Vec::new(), 0);
+        render(&mut r, &gr, [0.0, 0.0]);
+        assert_eq!(r.1, 1);
+    }
+
+    #[test]
+    fn hover() {
+        let gr = Group(vec![
+            Rectangle(10.0, 10.0).click(Action::EndTurn),
+            Rectangle(10.0, 10.0).translate([5.0, 0.0]).click(Action::StartRoad),
+        ]);
+        let mut r = Recorder(Vec::new(), 0);
+        assert_eq!(render(&mut r, &gr, [2.0, 2.0]), Some(Action::EndTurn));
+        assert_eq!(render(&mut r, &gr, [7.0, 2.0]), Some(Action::StartRoad));
+        assert_eq!(render(&mut r, &gr, [20.0, 2.0]), None);
+    }
+}
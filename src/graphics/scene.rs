@@ -0,0 +1,386 @@
+//! The scene tree, that is built by the game and drawn by the renderer.
+
+use std::collections::HashMap;
+use game::Action;
+
+#[derive(Clone,Debug)]
+pub enum Graphics {
+    Rectangle(f64,f64),
+    Color([f32;4], Box<Graphics>),
+    Translate([f64;2], Box<Graphics>),
+    Scale(f64, Box<Graphics>),
+    /// Text with the baseline of the first line at the origin. Lines are
+    /// separated by `\n`.
+    Text(u32, String),
+    /// Text aligned in a block of the width. Longer lines are wrapped
+    /// between words.
+    TextBlock(u32, String, Align, f64),
+    Group(Vec<Graphics>),
+    Click(Action, Box<Graphics>),
+    /// Children positioned by their bounding boxes.
+    Arrange(Layout, Vec<Graphics>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+impl Align {
+    /// Position of something of length `len` in a space of length `space`.
+    pub fn offset(self, len: f64, space: f64) -> f64 {
+        match self {
+            Align::Start    => 0.0,
+            Align::Center   => (space-len)/2.0,
+            Align::End      => space-len,
+        }
+    }
+}
+
+/// How the children of `Graphics::Arrange` are positioned. The top left
+/// corner of the arrangement is at the origin.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layout {
+    /// Left to right with the spacing between them, aligned vertically.
+    Row(f64, Align),
+    /// Top to bottom with the spacing between them, aligned horizontally.
+    Column(f64, Align),
+    /// Rows of the given number of columns with the spacing between the
+    /// cells. All cells are as big as the largest child.
+    Grid(usize, f64),
+    /// Space around the children.
+    Padding(f64),
+    /// Aligned horizontally and vertically in a box of the size.
+    Cell([f64;2], Align, Align),
+}
+
+impl Layout {
+    /// Offsets of the children with the bounding boxes `boxes`.
+    pub fn offsets(&self, boxes: &[Option<[f64;4]>]) -> Vec<[f64;2]> {
+        let max = |k: usize| boxes.iter().filter_map(|b| b.map(|b| b[k])).fold(0.0, f64::max);
+        match self {
+            &Layout::Row(spacing, align) => {
+                let height = max(3);
+                let mut x = 0.0;
+                boxes.iter().map(|b| match b {
+                    &Some(b) => {
+                        let o = [x-b[0], align.offset(b[3], height)-b[1]];
+                        x += b[2]+spacing;
+                        o
+                    }
+                    &None => [x, 0.0],
+                }).collect()
+            }
+            &Layout::Column(spacing, align) => {
+                let width = max(2);
+                let mut y = 0.0;
+                boxes.iter().map(|b| match b {
+                    &Some(b) => {
+                        let o = [align.offset(b[2], width)-b[0], y-b[1]];
+                        y += b[3]+spacing;
+                        o
+                    }
+                    &None => [0.0, y],
+                }).collect()
+            }
+            &Layout::Grid(columns, spacing) => {
+                let columns = columns.max(1);
+                let cell = [max(2)+spacing, max(3)+spacing];
+                boxes.iter().enumerate().map(|(i, b)| {
+                    let b = b.unwrap_or([0.0; 4]);
+                    [(i%columns) as f64*cell[0]-b[0], (i/columns) as f64*cell[1]-b[1]]
+                }).collect()
+            }
+            &Layout::Padding(p) => {
+                let b = bounds(boxes.to_vec()).unwrap_or([0.0; 4]);
+                vec![[p-b[0], p-b[1]]; boxes.len()]
+            }
+            &Layout::Cell(size, h, v) => {
+                let b = bounds(boxes.to_vec()).unwrap_or([0.0; 4]);
+                let o = [h.offset(b[2], size[0])-b[0], v.offset(b[3], size[1])-b[1]];
+                vec![o; boxes.len()]
+            }
+        }
+    }
+}
+
+/// Offsets of the children of `Graphics::Arrange` nodes, by the address of
+/// the node.
+pub type Arrangements = HashMap<*const Graphics, Vec<[f64;2]>>;
+
+impl Graphics {
+    pub fn color(self, col: [f32;4]) -> Graphics {
+        Graphics::Color(col, Box::new(self))
+    }
+    pub fn translate(self, v: [f64;2]) -> Graphics {
+        Graphics::Translate(v, Box::new(self))
+    }
+    pub fn scale(self, s: f64) -> Graphics {
+        Graphics::Scale(s, Box::new(self))
+    }
+    pub fn click(self, ac: Action) -> Graphics {
+        Graphics::Click(ac, Box::new(self))
+    }
+
+    pub fn text_block(size: u32, txt: String, width: f64, align: Align) -> Graphics {
+        Graphics::TextBlock(size, txt, align, width)
+    }
+
+    pub fn row(children: Vec<Graphics>, spacing: f64) -> Graphics {
+        Graphics::Arrange(Layout::Row(spacing, Align::Start), children)
+    }
+    pub fn column(children: Vec<Graphics>, spacing: f64) -> Graphics {
+        Graphics::Arrange(Layout::Column(spacing, Align::Start), children)
+    }
+    pub fn grid(children: Vec<Graphics>, columns: usize, spacing: f64) -> Graphics {
+        Graphics::Arrange(Layout::Grid(columns, spacing), children)
+    }
+    pub fn padding(self, p: f64) -> Graphics {
+        Graphics::Arrange(Layout::Padding(p), vec![self])
+    }
+    pub fn cell(self, size: [f64;2], h: Align, v: Align) -> Graphics {
+        Graphics::Arrange(Layout::Cell(size, h, v), vec![self])
+    }
+    /// Place at an edge, a corner or the centre of a window of size `view`,
+    /// `margin` away from its edges.
+    pub fn anchor(self, view: [f64;2], h: Align, v: Align, margin: f64) -> Graphics {
+        self.padding(margin).cell(view, h, v)
+    }
+
+    /// Bounding box `[x, y, width, height]` in the coordinates of the parent.
+    /// `None`, if nothing is drawn.
+    pub fn bbox<M: TextMetrics>(&self, metrics: &mut M) -> Option<[f64;4]> {
+        self.measure(metrics, &mut Arrangements::new())
+    }
+
+    /// Bounding box like `bbox`. Remembers the offsets of the arranged
+    /// children in the subtree, so every node is measured only once.
+    pub fn measure<M: TextMetrics>(&self, metrics: &mut M, arrangements: &mut Arrangements)
+        -> Option<[f64;4]>
+    {
+        use self::Graphics::*;
+        match self {
+            &Rectangle(w,h) => Some([0.0, 0.0, w, h]),
+            &Translate(v, ref gr)  => gr.measure(metrics, arrangements).map(|r| {
+                [r[0]+v[0], r[1]+v[1], r[2], r[3]]
+            }),
+            &Scale(s, ref gr) => gr.measure(metrics, arrangements).map(|r| {
+                [r[0]*s, r[1]*s, r[2]*s, r[3]*s]
+            }),
+            &Color(_, ref gr) | &Click(_, ref gr) => {
+                gr.measure(metrics, arrangements)
+            }
+            &Text(size, ref txt) => {
+                let lines = text_lines(metrics, size, txt, None);
+                let width = lines.iter()
+                    .map(|l| metrics.text_width(size, l))
+                    .fold(0.0, f64::max);
+                Some(text_box(size, width, lines.len()))
+            }
+            &TextBlock(size, ref txt, _, width) => {
+                let lines = text_lines(metrics, size, txt, Some(width));
+                Some(text_box(size, width, lines.len()))
+            }
+            &Group(ref children) => {
+                let boxes = children.iter().map(|c| c.measure(metrics, arrangements)).collect();
+                bounds(boxes)
+            }
+            &Arrange(ref layout, ref children) => {
+                let boxes: Vec<_> = children.iter()
+                    .map(|c| c.measure(metrics, arrangements))
+                    .collect();
+                let offsets = layout.offsets(&boxes);
+                let moved = boxes.iter().zip(offsets.iter()).map(|(b, o)| {
+                    b.map(|r| [r[0]+o[0], r[1]+o[1], r[2], r[3]])
+                }).collect();
+                arrangements.insert(self as *const Graphics, offsets);
+                let content = bounds(moved);
+                match layout {
+                    &Layout::Padding(p) => content.map(|r| [0.0, 0.0, r[2]+2.0*p, r[3]+2.0*p]),
+                    &Layout::Cell(size, _, _) => Some([0.0, 0.0, size[0], size[1]]),
+                    _ => content,
+                }
+            }
+        }
+    }
+}
+
+/// Smallest rectangle containing all the boxes.
+fn bounds(boxes: Vec<Option<[f64;4]>>) -> Option<[f64;4]> {
+    boxes.into_iter()
+        .filter_map(|b| b)
+        .fold(None, |acc, r| Some(match acc {
+            Some(a) => union(a, r),
+            None => r,
+        }))
+}
+
+/// Space below the baseline of a text, relative to the font size.
+pub const TEXT_DESCENT: f64 = 0.25;
+/// Distance between the baselines of two lines, relative to the font size.
+pub const LINE_HEIGHT: f64 = 1.3;
+
+/// The lines of a text. With a width, lines longer than it are broken
+/// between words. Single words, that are too long, are not broken.
+pub fn text_lines<M: TextMetrics>(metrics: &mut M, size: u32, txt: &str, width: Option<f64>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in txt.split('\n') {
+        let width = match width {
+            Some(w) => w,
+            None => {
+                lines.push(paragraph.to_string());
+                continue;
+            }
+        };
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if line.is_empty() {
+                line = word.to_string();
+                continue;
+            }
+            let longer = format!("{} {}", line, word);
+            if metrics.text_width(size, &longer)<=width {
+                line = longer;
+            } else {
+                lines.push(line);
+                line = word.to_string();
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Bounding box of `lines` lines of text.
+fn text_box(size: u32, width: f64, lines: usize) -> [f64;4] {
+    let size = size as f64;
+    let height = size*(1.0+TEXT_DESCENT) + size*LINE_HEIGHT*(lines.max(1)-1) as f64;
+    [0.0, -size, width, height]
+}
+
+/// Measures text for bounding boxes. Text is one font size high above the
+/// baseline.
+pub trait TextMetrics {
+    /// Width of a line of text.
+    fn text_width(&mut self, size: u32, txt: &str) -> f64;
+}
+
+/// Smallest rectangle containing both.
+pub fn union(a: [f64;4], b: [f64;4]) -> [f64;4] {
+    let x = a[0].min(b[0]);
+    let y = a[1].min(b[1]);
+    let x1 = (a[0]+a[2]).max(b[0]+b[2]);
+    let y1 = (a[1]+a[3]).max(b[1]+b[3]);
+    [x, y, x1-x, y1-y]
+}
+
+/// Do the rectangles overlap, e.g. is a node visible on the screen?
+pub fn intersects(a: [f64;4], b: [f64;4]) -> bool {
+    a[0]<b[0]+b[2] && b[0]<a[0]+a[2] && a[1]<b[1]+b[3] && b[1]<a[1]+a[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Graphics::*;
+
+    /// Every character is half the font size wide.
+    struct Metrics;
+
+    impl TextMetrics for Metrics {
+        fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+            txt.chars().count() as f64*size as f64/2.0
+        }
+    }
+
+    /// Counts the measured lines.
+    struct Counter(usize);
+
+    impl TextMetrics for Counter {
+        fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+            self.0 += 1;
+            0.0
+        }
+    }
+
+    fn boxes(children: &[Graphics]) -> Vec<Option<[f64;4]>> {
+        children.iter().map(|c| c.bbox(&mut Metrics)).collect()
+    }
+
+    #[test]
+    fn bbox() {
+        let m = &mut Metrics;
+        assert_eq!(Rectangle(2.0, 3.0).translate([1.0, 1.0]).scale(2.0).bbox(m),
+                   Some([2.0, 2.0, 4.0, 6.0]));
+        assert_eq!(Text(10, "abcd".to_string()).color([1.0; 4]).bbox(m),
+                   Some([0.0, -10.0, 20.0, 12.5]));
+        assert_eq!(Group(vec![]).bbox(m), None);
+
+        let gr = Group(vec![
+            Rectangle(2.0, 2.0).click(Action::EndTurn),
+            Group(vec![]).translate([-10.0, -10.0]),
+            Rectangle(1.0, 1.0).translate([5.0, 3.0]),
+        ]);
+        assert_eq!(gr.bbox(m), Some([0.0, 0.0, 6.0, 4.0]));
+    }
+
+    #[test]
+    fn layout() {
+        let m = &mut Metrics;
+        let rects = || vec![Rectangle(2.0, 4.0), Rectangle(6.0, 2.0).translate([-1.0, -1.0])];
+
+        let row = Graphics::row(rects(), 1.0);
+        assert_eq!(row.bbox(m), Some([0.0, 0.0, 9.0, 4.0]));
+        assert_eq!(Layout::Row(1.0, Align::End).offsets(&boxes(&rects())),
+                   vec![[0.0, 0.0], [4.0, 3.0]]);
+        assert_eq!(Layout::Column(0.0, Align::Center).offsets(&boxes(&rects())),
+                   vec![[2.0, 0.0], [1.0, 5.0]]);
+        assert_eq!(Layout::Grid(1, 1.0).offsets(&boxes(&rects())),
+                   vec![[0.0, 0.0], [1.0, 6.0]]);
+
+        let text = Text(10, "ab".to_string());
+        assert_eq!(text.clone().padding(2.0).bbox(m), Some([0.0, 0.0, 14.0, 16.5]));
+        assert_eq!(Layout::Padding(2.0).offsets(&boxes(&[text.clone()])), vec![[2.0, 12.0]]);
+        let centred = text.cell([20.0, 20.0], Align::Center, Align::Center);
+        assert_eq!(centred.bbox(m), Some([0.0, 0.0, 20.0, 20.0]));
+
+        let anchored = Rectangle(10.0, 10.0).anchor([100.0, 50.0], Align::End, Align::End, 5.0);
+        assert_eq!(Layout::Cell([100.0, 50.0], Align::End, Align::End)
+                       .offsets(&boxes(&[Rectangle(10.0, 10.0).padding(5.0)])),
+                   vec![[80.0, 30.0]]);
+        assert_eq!(anchored.bbox(m), Some([0.0, 0.0, 100.0, 50.0]));
+    }
+
+    #[test]
+    fn measure_once() {
+        let gr = Text(10, "ab".to_string()).padding(1.0).padding(1.0).padding(1.0);
+        let mut counter = Counter(0);
+        let mut arrangements = Arrangements::new();
+        gr.measure(&mut counter, &mut arrangements);
+        assert_eq!(counter.0, 1);
+        assert_eq!(arrangements.len(), 3);
+        assert_eq!(arrangements[&(&gr as *const Graphics)], vec![[1.0, 1.0]]);
+    }
+
+    #[test]
+    fn text() {
+        let m = &mut Metrics;
+        assert_eq!(text_lines(m, 2, "ab cd\nef", None), vec!["ab cd", "ef"]);
+        assert_eq!(text_lines(m, 2, "ab cd  efg h\n\nij", Some(5.0)),
+                   vec!["ab cd", "efg h", "", "ij"]);
+        assert_eq!(text_lines(m, 2, "abcdefgh ij", Some(4.0)), vec!["abcdefgh", "ij"]);
+
+        assert_eq!(Text(10, "ab\nabcd".to_string()).bbox(m), Some([0.0, -10.0, 20.0, 25.5]));
+        let block = Graphics::text_block(10, "ab ab".to_string(), 12.0, Align::Center);
+        assert_eq!(block.bbox(m), Some([0.0, -10.0, 12.0, 25.5]));
+    }
+
+    #[test]
+    fn rects() {
+        assert_eq!(union([0.0, 0.0, 1.0, 1.0], [2.0, -1.0, 1.0, 1.0]), [0.0, -1.0, 3.0, 2.0]);
+        assert!(intersects([0.0, 0.0, 2.0, 2.0], [1.0, 1.0, 2.0, 2.0]));
+        assert!(!intersects([0.0, 0.0, 1.0, 1.0], [1.0, 0.0, 1.0, 1.0]));
+    }
+}
@@ -0,0 +1,198 @@
+//! Snapshot tests: scenes are rendered with the software renderer and
+//! compared to reference images in `tests/snapshots`.
+//!
+//! Set `UPDATE_SNAPSHOTS=1` to write new references or replace all of them
+//! after an intended change. Without it a missing reference is an error. When
+//! an image differs, `<name>.actual.png` and `<name>.diff.png` are written
+//! next to the reference.
+
+extern crate png;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use self::png::HasParameters;
+use super::*;
+
+/// How much a rendered image may differ from its reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference of a channel, that still counts as equal.
+    pub channel: u8,
+    /// Number of pixels, that may differ.
+    pub pixels: usize,
+}
+
+pub const DEFAULT_TOLERANCE: Tolerance = Tolerance { channel: 2, pixels: 0 };
+
+impl Canvas {
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+
+    /// Load an image with 8 bit RGBA pixels.
+    pub fn load_png<P: AsRef<Path>>(path: P) -> io::Result<Canvas> {
+        let decoder = png::Decoder::new(File::open(path)?);
+        let (info, mut reader) = decoder.read_info()?;
+        if info.color_type!=png::ColorType::RGBA || info.bit_depth!=png::BitDepth::Eight {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "expected 8 bit RGBA"));
+        }
+        let mut canvas = Canvas::new(info.width, info.height);
+        reader.next_frame(&mut canvas.pixels)?;
+        Ok(canvas)
+    }
+
+    /// Compare with an image of the same size. Returns the number of pixels,
+    /// that differ by more than `tolerance` in any channel, and an image
+    /// showing them in red over a faded copy of `self`.
+    pub fn diff(&self, other: &Canvas, tolerance: u8) -> (usize, Canvas) {
+        assert_eq!((self.width, self.height), (other.width, other.height));
+        let mut wrong = 0;
+        let mut diff = Canvas::new(self.width, self.height);
+        let pixels = self.pixels.chunks(4).zip(other.pixels.chunks(4));
+        for ((a, b), d) in pixels.zip(diff.pixels.chunks_mut(4)) {
+            let differs = a.iter().zip(b.iter())
+                .any(|(&x, &y)| (x as i32-y as i32).abs()>tolerance as i32);
+            if differs {
+                wrong += 1;
+                d.copy_from_slice(&[255, 0, 0, 255]);
+            } else {
+                let grey = (a[0] as u32 + a[1] as u32 + a[2] as u32)/3;
+                let faded = (192 + grey/4) as u8;
+                d.copy_from_slice(&[faded, faded, faded, 255]);
+            }
+        }
+        (wrong, diff)
+    }
+}
+
+/// Render a scene onto a white canvas.
+pub fn render_image(gr: &Graphics, width: u32, height: u32) -> Canvas {
+    let mut canvas = Canvas::new(width, height);
+    canvas.clear([1.0, 1.0, 1.0, 1.0]);
+    render(&mut canvas, gr, [-1.0, -1.0]);
+    canvas
+}
+
+pub fn snapshot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots")
+}
+
+/// Compare the image to the reference `name`.
+pub fn check_snapshot(name: &str, image: &Canvas, tolerance: Tolerance) -> Result<(), String> {
+    let dir = snapshot_dir();
+    let reference = dir.join(format!("{}.png", name));
+    let actual = dir.join(format!("{}.actual.png", name));
+    let diff_file = dir.join(format!("{}.diff.png", name));
+    let _ = fs::remove_file(&actual);
+    let _ = fs::remove_file(&diff_file);
+
+    if env::var("UPDATE_SNAPSHOTS").is_ok() {
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        return image.save_png(&reference)
+            .map_err(|e| format!("{}: {}", reference.display(), e));
+    }
+    if !reference.exists() {
+        return Err(format!("{}: missing, run the tests with UPDATE_SNAPSHOTS=1 to create it",
+                           reference.display()));
+    }
+
+    let expected = Canvas::load_png(&reference)
+        .map_err(|e| format!("{}: {}", reference.display(), e))?;
+    let save_actual = || image.save_png(&actual).map_err(|e| e.to_string());
+    if (expected.width, expected.height)!=(image.width, image.height) {
+        save_actual()?;
+        return Err(format!("{}: size {}x{}, but the image is {}x{}",
+                           reference.display(), expected.width, expected.height,
+                           image.width, image.height));
+    }
+    let (wrong, diff) = image.diff(&expected, tolerance.channel);
+    if wrong>tolerance.pixels {
+        save_actual()?;
+        diff.save_png(&diff_file).map_err(|e| e.to_string())?;
+        return Err(format!("{}: {} pixels differ, see {}",
+                           reference.display(), wrong, diff_file.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::*;
+
+    fn assert_snapshot(name: &str, gr: &Graphics, width: u32, height: u32) {
+        let image = render_image(gr, width, height);
+        if let Err(e) = check_snapshot(name, &image, DEFAULT_TOLERANCE) {
+            panic!("{}", e);
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let mut a = Canvas::new(2, 1);
+        a.clear([1.0, 1.0, 1.0, 1.0]);
+        let mut b = a.clone();
+        b.pixels[0] = 250;
+        assert_eq!(a.diff(&b, 2).0, 1);
+        assert_eq!(a.diff(&b, 5).0, 0);
+        assert_eq!(a.diff(&b, 2).1.pixel(0, 0), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn missing_reference() {
+        if env::var("UPDATE_SNAPSHOTS").is_ok() {
+            return;
+        }
+        let image = Canvas::new(1, 1);
+        assert!(check_snapshot("missing", &image, DEFAULT_TOLERANCE).is_err());
+        assert!(!snapshot_dir().join("missing.png").exists());
+    }
+
+    #[test]
+    fn card() {
+        let gr = Card::named("farm").unwrap().draw().scale(2.0);
+        assert_snapshot("card_farm", &gr, 80, 120);
+    }
+
+    #[test]
+    fn deck() {
+        let hand = ["farm", "lumber", "quarry", "guilds"].iter()
+            .map(|n| Card::named(n).unwrap())
+            .collect();
+        let deck = Deck::from_piles(vec![Card::named("farm").unwrap()], hand, vec![]);
+        assert_snapshot("deck", &deck.draw(300.0, &State::Def), 300, 100);
+    }
+
+    #[test]
+    fn map() {
+        let mut map = test_map();
+        map.stock.wood = 100;
+        map.place_card((0,1), Card::named("farm").unwrap());
+        map.build_road((1,2));
+        assert_snapshot("map", &map.build_graphics(&State::Def), 200, 300);
+
+        let state = State::PlaceCard(Card::named("farm").unwrap(), 0);
+        assert_snapshot("map_place_farm", &map.build_graphics(&state), 200, 300);
+    }
+
+    #[test]
+    fn event() {
+        assert_snapshot("event", &Event::PopulationBoom((3,4)).draw(), 260, 110);
+    }
+
+    #[test]
+    fn screen() {
+        let game = ::Game::new(Session::new(test_map(), 2));
+        for &(w, h) in [(640, 480), (480, 640)].iter() {
+            let gr = game.graphics([w as f64, h as f64], [0.0, 0.0], &mut BlockFont);
+            assert_snapshot(&format!("screen_{}x{}", w, h), &gr, w, h);
+        }
+    }
+}
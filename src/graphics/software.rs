@@ -0,0 +1,176 @@
+//! Software renderer into an RGBA image, for tests and screenshots on
+//! machines without a GPU.
+//!
+//! There are no fonts: every character of a text is drawn as a solid block,
+//! so images don't depend on the fonts installed.
+
+use super::render::*;
+use super::TextMetrics;
+
+/// Horizontal advance of a character, relative to the font size.
+pub const GLYPH_ADVANCE: f64 = 0.6;
+/// Width of the block drawn for a character, relative to the font size.
+const GLYPH_WIDTH: f64 = 0.5;
+/// Height of the block above the baseline, relative to the font size.
+const GLYPH_HEIGHT: f64 = 0.7;
+
+/// Metrics of the blocks drawn for text.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockFont;
+
+impl TextMetrics for BlockFont {
+    fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+        txt.chars().count() as f64*GLYPH_ADVANCE*size as f64
+    }
+}
+
+/// An RGBA image with 8 bits per channel, row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// A transparent canvas.
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            width: width,
+            height: height,
+            pixels: vec![0; (width*height*4) as usize],
+        }
+    }
+
+    pub fn clear(&mut self, color: [f32;4]) {
+        let c = to_rgba(color);
+        for p in self.pixels.chunks_mut(4) {
+            p.copy_from_slice(&c);
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8;4] {
+        let i = ((y*self.width + x)*4) as usize;
+        [self.pixels[i], self.pixels[i+1], self.pixels[i+2], self.pixels[i+3]]
+    }
+
+    /// Blend `color` over the pixel.
+    fn blend(&mut self, x: u32, y: u32, color: [f32;4]) {
+        let i = ((y*self.width + x)*4) as usize;
+        let a = color[3].max(0.0).min(1.0);
+        for k in 0..3 {
+            let old = self.pixels[i+k] as f32/255.0;
+            self.pixels[i+k] = channel(color[k]*a + old*(1.0-a));
+        }
+        let old = self.pixels[i+3] as f32/255.0;
+        self.pixels[i+3] = channel(a + old*(1.0-a));
+    }
+}
+
+fn channel(v: f32) -> u8 {
+    (v.max(0.0).min(1.0)*255.0).round() as u8
+}
+
+fn to_rgba(color: [f32;4]) -> [u8;4] {
+    [channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3])]
+}
+
+impl TextMetrics for Canvas {
+    fn text_width(&mut self, size: u32, txt: &str) -> f64 {
+        BlockFont.text_width(size, txt)
+    }
+}
+
+impl Renderer for Canvas {
+    /// Fills every pixel, whose centre lies inside the transformed rectangle.
+    fn rectangle(&mut self, color: [f32;4], size: [f64;2], trans: Matrix2d) {
+        let corners = [[0.0, 0.0], [size[0], 0.0], [0.0, size[1]], size];
+        let (mut min, mut max) = ([::std::f64::MAX; 2], [::std::f64::MIN; 2]);
+        for c in corners.iter() {
+            let p = transform_pos(trans, *c);
+            for k in 0..2 {
+                min[k] = min[k].min(p[k]);
+                max[k] = max[k].max(p[k]);
+            }
+        }
+        let x0 = min[0].floor().max(0.0) as u32;
+        let y0 = min[1].floor().max(0.0) as u32;
+        let x1 = max[0].ceil().min(self.width as f64).max(0.0) as u32;
+        let y1 = max[1].ceil().min(self.height as f64).max(0.0) as u32;
+
+        let inv = invert(trans);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let p = transform_pos(inv, [x as f64+0.5, y as f64+0.5]);
+                if p[0]>=0.0 && p[1]>=0.0 && p[0]<size[0] && p[1]<size[1] {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, color: [f32;4], size: u32, txt: &str, trans: Matrix2d) {
+        let size = size as f64;
+        for (i, c) in txt.chars().enumerate() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let pos = [i as f64*GLYPH_ADVANCE*size, -GLYPH_HEIGHT*size];
+            self.rectangle(color, [GLYPH_WIDTH*size, GLYPH_HEIGHT*size], translate(trans, pos));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Align, Graphics};
+    use super::super::Graphics::*;
+
+    #[test]
+    fn rectangles() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.clear([1.0, 1.0, 1.0, 1.0]);
+        let gr = Group(vec![
+            Rectangle(2.0, 3.0).translate([1.0, 1.0]).scale(2.0).color([1.0, 0.0, 0.0, 1.0]),
+            Rectangle(4.0, 4.0).translate([10.0, 10.0]).color([0.0, 0.0, 1.0, 0.5]),
+        ]);
+        render(&mut canvas, &gr, [0.0, 0.0]);
+        assert_eq!(canvas.pixel(1, 1), [255, 255, 255, 255]);
+        assert_eq!(canvas.pixel(2, 2), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel(5, 7), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel(6, 7), [255, 255, 255, 255]);
+        assert_eq!(canvas.pixel(5, 8), [255, 255, 255, 255]);
+        assert_eq!(canvas.pixel(12, 12), [128, 128, 255, 255]);
+    }
+
+    #[test]
+    fn clipping_and_text() {
+        let mut canvas = Canvas::new(10, 10);
+        let gr = Group(vec![
+            Rectangle(100.0, 100.0).translate([-50.0, 5.0]).color([0.0, 1.0, 0.0, 1.0]),
+            Text(10, "a b".to_string()).translate([0.0, 10.0]),
+        ]);
+        render(&mut canvas, &gr, [0.0, 0.0]);
+        assert_eq!(canvas.pixel(9, 9), [0, 255, 0, 255]);
+        assert_eq!(canvas.pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(canvas.pixel(2, 4), [0, 0, 0, 255]);
+        assert_eq!(canvas.pixel(7, 4), [0, 0, 0, 0]);
+        assert_eq!(BlockFont.text_width(10, "a b"), 18.0);
+    }
+
+    #[test]
+    fn text_blocks() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let mut canvas = Canvas::new(20, 40);
+        let gr = Graphics::text_block(10, "a\nb".to_string(), 20.0, Align::End)
+            .color(red)
+            .translate([0.0, 10.0]);
+        render(&mut canvas, &gr, [0.0, 0.0]);
+        // The lines are right aligned and the second one is one line lower.
+        assert_eq!(canvas.pixel(16, 5), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel(16, 18), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel(16, 12), [0, 0, 0, 0]);
+        assert_eq!(canvas.pixel(12, 5), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,19 @@
+#![allow(unused_variables, unused_imports)]
+
+#[cfg(test)]
+#[macro_use]
+extern crate quickcheck;
+
+extern crate piston_window;
+extern crate vecmath;
+extern crate assert;
+extern crate rand;
+#[macro_use]
+extern crate lazy_static;
+
+pub mod graphics;
+pub mod game;
+mod app;
+
+pub use graphics::Graphics;
+pub use app::*;